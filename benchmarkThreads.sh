@@ -0,0 +1,19 @@
+#!/bin/bash
+
+# Compare the hash rate of ore-cli builds at and above the number of physical cores of this computer,
+# e.g. a build from before and after a change to the hashing engine:
+#   ./benchmarkThreads.sh ./ore-before ./target/release/ore
+
+BINARIES=${@:-./target/release/ore}
+PHYSICAL_CORES=$(lscpu -p=Core,Socket | grep -v '^#' | sort -u | wc -l)
+LOGICAL_CPUS=$(nproc)
+THREAD_COUNTS=$(echo ${PHYSICAL_CORES} ${LOGICAL_CPUS} $((PHYSICAL_CORES * 2)) | tr ' ' '\n' | sort -nu)
+
+echo "Physical cores: ${PHYSICAL_CORES}   Logical CPUs: ${LOGICAL_CPUS}"
+for THREADS in ${THREAD_COUNTS}; do
+	for BIN in ${BINARIES}; do
+		# The result is shown by a spinner, which is only drawn on a terminal wide enough for it
+		RESULT=$(script -q -c "stty cols 200 rows 50; ${BIN} benchmark --threads ${THREADS}" /dev/null | tr '\r' '\n' | grep -o "Hashpower:.*" | tail -1 | sed "s/ *$//")
+		echo "${THREADS} threads   ${BIN}   ${RESULT}"
+	done
+done
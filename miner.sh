@@ -19,6 +19,7 @@ while true; do
 	echo Priority fee:		${FEE}
 	echo Threads:			${THREADS}
 	echo Buffer Time:		${BUFFER_TIME}
+	echo Max Overtime:		${MAX_OVERTIME}
 	echo ore-cli:			${ORE_BIN}
 
 	# rotate any previous logs to keep last 6
@@ -41,16 +42,36 @@ while true; do
 	WALLET_NAME=${KEY##*/}
 	WALLET_NAME=${WALLET_NAME%.*}
 	export WALLET_NAME
+	# session stats are kept per wallet and survive restarts of the miner
+	SESSION_STATEFILE="./logs/${WALLET_NAME}.session.json"
+	export SESSION_STATEFILE
 	export MINER_WATTAGE_IDLE
 	export MINER_WATTAGE_BUSY
 	export MINER_COST_PER_KILOWATT_HOUR 
 	export MINER_DESIRED_DIFFICULTY_LEVEL 
+	export MINER_DIFFICULTY_STRATEGY
+	export MINER_DIFFICULTY_PERCENTILE
+	export MINER_SCHEDULE
 	
 	# start the miner
-	COMMAND="${ORE_BIN} mine --rpc ${RPC_URL} --keypair ${KEY} --priority-fee=${FEE:-0} --threads ${THREADS:-1} --buffer-time ${BUFFER_TIME:-2}"
+	COMMAND="${ORE_BIN} mine --rpc ${RPC_URL} --keypair ${KEY} --priority-fee=${FEE:-0} --threads ${THREADS:-1} --buffer-time ${BUFFER_TIME:-2} --max-overtime ${MAX_OVERTIME:-10} --min-sol-balance ${MIN_SOL_BALANCE:-0.005} --resubmit-attempts ${RESUBMIT_ATTEMPTS:-2} --resubmit-fee-increase ${RESUBMIT_FEE_INCREASE:-50} --reset-policy ${RESET_POLICY:-probabilistic} --reset-rate ${RESET_RATE:-1} --bus-strategy ${BUS_STRATEGY:-richest}"
+	[ -n "${KEYPAIRS}" ] && COMMAND="${COMMAND} --keypairs ${KEYPAIRS}"
+	[ -n "${MAX_CPU_TEMP}" ] && COMMAND="${COMMAND} --max-cpu-temp ${MAX_CPU_TEMP}"
+	[ -n "${RESUME_CPU_TEMP}" ] && COMMAND="${COMMAND} --resume-cpu-temp ${RESUME_CPU_TEMP}"
+	[ -n "${CPU_CORES}" ] && COMMAND="${COMMAND} --cpu-cores ${CPU_CORES}"
+	[ "${SKIP_SMT}" = "true" ] && COMMAND="${COMMAND} --skip-smt"
+	[ -n "${NICE}" ] && COMMAND="${COMMAND} --nice ${NICE}"
+	[ -n "${AUTO_CLAIM_THRESHOLD}" ] && COMMAND="${COMMAND} --auto-claim-threshold ${AUTO_CLAIM_THRESHOLD}"
+	[ -n "${AUTO_CLAIM_EVERY}" ] && COMMAND="${COMMAND} --auto-claim-every ${AUTO_CLAIM_EVERY}"
+	[ -n "${CLAIM_TO}" ] && COMMAND="${COMMAND} --claim-to ${CLAIM_TO}"
 	# echo ${COMMAND}
 	eval $COMMAND
-	[ $? -eq 0 ] && break
+	# 0 is a requested shutdown, 130 an immediate stop from a second CTRL+C and 10-13 a bounded run that has
+	# reached its limit. Anything else is restarted.
+	EXIT_CODE=$?
+	[ ${EXIT_CODE} -eq 0 ] && break
+	[ ${EXIT_CODE} -eq 130 ] && break
+	[ ${EXIT_CODE} -ge 10 ] && [ ${EXIT_CODE} -le 13 ] && break
 
 	echo ------------------------------------------------------------------------------------------------------------------------
 	echo `date +'%Y-%m-%d %H:%M:%S'` "Restarting miner process in 10 seconds..."
@@ -16,31 +16,78 @@ FEENO=PRIORITY_FEE$1
 WATTAGEIDLENO=MINER_WATTAGE_IDLE$1
 WATTAGEBUSYNO=MINER_WATTAGE_BUSY$1
 BUFFER_TIMENO=BUFFER_TIME$1
+MAX_OVERTIMENO=MAX_OVERTIME$1
+MIN_SOL_BALANCENO=MIN_SOL_BALANCE$1
+RESUBMIT_ATTEMPTSNO=RESUBMIT_ATTEMPTS$1
+RESUBMIT_FEE_INCREASENO=RESUBMIT_FEE_INCREASE$1
+RESET_POLICYNO=RESET_POLICY$1
+RESET_RATENO=RESET_RATE$1
+BUS_STRATEGYNO=BUS_STRATEGY$1
+KEYPAIRSNO=KEYPAIRS$1
+MAX_CPU_TEMPNO=MAX_CPU_TEMP$1
+RESUME_CPU_TEMPNO=RESUME_CPU_TEMP$1
+CPU_CORESNO=CPU_CORES$1
+SKIP_SMTNO=SKIP_SMT$1
+NICENO=NICE$1
 DESIRED_DIFFICULTY_LEVELNO=DESIRED_DIFFICULTY_LEVEL$1
+DIFFICULTY_STRATEGYNO=DIFFICULTY_STRATEGY$1
+DIFFICULTY_PERCENTILENO=DIFFICULTY_PERCENTILE$1
+SCHEDULENO=SCHEDULE$1
 CLOUD_COST_PER_HOURNO=CLOUD_COST_PER_HOUR$1
 LOG_WEBHOOKNO=LOG_WEBHOOK$1
+STATS_REPORT_FORMATNO=STATS_REPORT_FORMAT$1
+AUTO_CLAIM_THRESHOLDNO=AUTO_CLAIM_THRESHOLD$1
+AUTO_CLAIM_EVERYNO=AUTO_CLAIM_EVERY$1
+CLAIM_TONO=CLAIM_TO$1
 
 RPC_URL=${!RPCNO}
 KEY=${!KEYNO}
 THREADS=${!THREADSNO}
 FEE=${!FEENO}
 BUFFER_TIME=${!BUFFER_TIMENO}
+MAX_OVERTIME=${!MAX_OVERTIMENO}
+MIN_SOL_BALANCE=${!MIN_SOL_BALANCENO}
+RESUBMIT_ATTEMPTS=${!RESUBMIT_ATTEMPTSNO}
+RESUBMIT_FEE_INCREASE=${!RESUBMIT_FEE_INCREASENO}
+RESET_POLICY=${!RESET_POLICYNO}
+RESET_RATE=${!RESET_RATENO}
+BUS_STRATEGY=${!BUS_STRATEGYNO}
+KEYPAIRS=${!KEYPAIRSNO}
+MAX_CPU_TEMP=${!MAX_CPU_TEMPNO}
+RESUME_CPU_TEMP=${!RESUME_CPU_TEMPNO}
+CPU_CORES=${!CPU_CORESNO}
+SKIP_SMT=${!SKIP_SMTNO}
+NICE=${!NICENO}
+AUTO_CLAIM_THRESHOLD=${!AUTO_CLAIM_THRESHOLDNO}
+AUTO_CLAIM_EVERY=${!AUTO_CLAIM_EVERYNO}
+CLAIM_TO=${!CLAIM_TONO}
 
 MINER_NAME="Miner ${1}"
 CLOUD_COST_PER_HOUR=${!CLOUD_COST_PER_HOURNO}
 MINER_DESIRED_DIFFICULTY_LEVEL=${!DESIRED_DIFFICULTY_LEVELNO}
+MINER_DIFFICULTY_STRATEGY=${!DIFFICULTY_STRATEGYNO}
+MINER_DIFFICULTY_PERCENTILE=${!DIFFICULTY_PERCENTILENO}
+MINER_SCHEDULE=${!SCHEDULENO}
+MINER_WATTAGE_IDLE=${!WATTAGEIDLENO}
+MINER_WATTAGE_BUSY=${!WATTAGEBUSYNO}
+MINER_COST_PER_KILOWATT_HOUR=${ELECTRICITY_COST_PER_KILOWATT_HOUR}
 export CLOUD_COST_PER_HOUR=${!CLOUD_COST_PER_HOURNO}
 export LOG_WEBHOOK=${!LOG_WEBHOOKNO}
+export STATS_REPORT_FORMAT=${!STATS_REPORT_FORMATNO}
 
 # echo RPC_URL:							${RPC_URL}
 # echo KEY: 							${KEY}
 # echo THREADS:							${THREADS}
 # echo FEE:								${FEE}
 # echo BUFFER_TIME:						${BUFFER_TIME}
+# echo MAX_OVERTIME:					${MAX_OVERTIME}
 # echo MINER_WATTAGE_IDLE:				${MINER_WATTAGE_IDLE}
 # echo MINER_WATTAGE_BUSY:				${MINER_WATTAGE_BUSY}
 # echo MINER_COST_PER_KILOWATT_HOUR:	${MINER_COST_PER_KILOWATT_HOUR}
 # echo MINER_DESIRED_DIFFICULTY_LEVEL:	${MINER_DESIRED_DIFFICULTY_LEVEL}
+# echo MINER_DIFFICULTY_STRATEGY:		${MINER_DIFFICULTY_STRATEGY}
+# echo MINER_DIFFICULTY_PERCENTILE:	${MINER_DIFFICULTY_PERCENTILE}
+# echo MINER_SCHEDULE:				${MINER_SCHEDULE}
 
 # Check that all required parameters have been specified for the miner number passed
 if [ -v ${RPC_URL} ]; then
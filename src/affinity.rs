@@ -0,0 +1,211 @@
+use std::fs::read_to_string;
+
+use crate::args::CpuPlacementArgs;
+
+// A list of CPUs given as numbers and ranges, e.g. 0-3,6
+#[derive(Clone, Debug)]
+pub struct CoreList(pub Vec<usize>);
+
+pub fn parse_core_list(value: &str) -> Result<CoreList, String> {
+    let mut cores = vec![];
+    for part in value.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        let (first, last) = part.split_once('-').unwrap_or((part, part));
+        let first = first.trim().parse::<usize>().map_err(|_| format!("invalid CPU {}", first))?;
+        let last = last.trim().parse::<usize>().map_err(|_| format!("invalid CPU {}", last))?;
+        if last < first {
+            return Err(format!("invalid CPU range {}", part));
+        }
+        for core in first..=last {
+            if !cores.contains(&core) {
+                cores.push(core);
+            }
+        }
+    }
+    if cores.is_empty() {
+        return Err("no CPUs given".to_string());
+    }
+    Ok(CoreList(cores))
+}
+
+// Where the hashing threads of a pool run and at what priority
+#[derive(Clone, Default)]
+pub struct CpuPlacement {
+    cores: Vec<usize>,              // The CPUs the threads are pinned to in turn, empty to leave them to the OS
+    pub skipped: Vec<usize>,        // The SMT siblings left out of the CPUs
+    nice: Option<i32>,
+    first: usize,                   // Where in the CPUs the pool's first thread goes
+}
+
+impl CpuPlacement {
+    pub fn new(args: &CpuPlacementArgs) -> Result<Self, String> {
+        let available = available_cpus();
+        let mut cores = match &args.cpu_cores {
+            Some(CoreList(cores)) => {
+                if let Some(core) = cores.iter().find(|core| !available.contains(core)) {
+                    return Err(format!("CPU {} is not available, the available CPUs are {}", core, join(&available)));
+                }
+                cores.clone()
+            }
+            // Skipping the SMT siblings without a list of CPUs pins one thread to each physical core
+            None if args.skip_smt => available,
+            None => vec![],
+        };
+
+        // Keep the first CPU of each physical core that was given and skip its siblings
+        let mut skipped = vec![];
+        if args.skip_smt {
+            let mut kept: Vec<usize> = vec![];
+            for core in cores {
+                if smt_siblings(core).iter().any(|sibling| kept.contains(sibling)) {
+                    skipped.push(core);
+                } else {
+                    kept.push(core);
+                }
+            }
+            cores = kept;
+        }
+
+        Ok(Self {
+            cores,
+            skipped,
+            nice: args.nice,
+            first: 0,
+        })
+    }
+
+    // The same placement with the first thread moved along the CPUs. Each wallet mined by the process starts
+    // where the previous wallet's threads end, so that the wallets do not share CPUs.
+    pub fn starting_at(&self, first: u64) -> Self {
+        Self {
+            first: first as usize,
+            ..self.clone()
+        }
+    }
+
+    // The CPUs threads are pinned to, taking more threads than CPUs in turn
+    pub fn core_for(&self, thread: usize) -> Option<usize> {
+        if self.cores.is_empty() {
+            return None;
+        }
+        Some(self.cores[(self.first + thread) % self.cores.len()])
+    }
+
+    // Move the calling hashing thread to its CPU and priority, returning where it actually ended up
+    pub fn apply(&self, thread: usize) -> ThreadPlacement {
+        let mut placed = ThreadPlacement::default();
+        if let Some(core) = self.core_for(thread) {
+            match pin_thread(core) {
+                Ok(()) => placed.cpu = Some(core),
+                Err(err) => placed.errors.push(err),
+            }
+        }
+        if let Some(nice) = self.nice {
+            match set_thread_nice(nice) {
+                Ok(()) => placed.nice = Some(nice),
+                Err(err) => placed.errors.push(err),
+            }
+        }
+        placed
+    }
+}
+
+// Where a hashing thread actually runs
+#[derive(Clone, Default)]
+pub struct ThreadPlacement {
+    pub cpu: Option<usize>,
+    pub nice: Option<i32>,
+    pub errors: Vec<String>,
+}
+
+// Describe where the threads of a pool run for the startup banner
+pub fn describe_placement(placement: &CpuPlacement, threads: &[ThreadPlacement]) -> String {
+    let cpus: Vec<usize> = threads.iter().filter_map(|thread| thread.cpu).collect();
+    let mut text = if cpus.is_empty() {
+        format!("{} threads, not pinned", threads.len())
+    } else {
+        format!("{} threads pinned to CPUs {}", threads.len(), join(&cpus))
+    };
+    if cpus.len() > placement.cores.len() {
+        text += " (more threads than CPUs, some CPUs run two threads)";
+    }
+    if !placement.skipped.is_empty() {
+        text += format!(", SMT siblings {} skipped", join(&placement.skipped)).as_str();
+    }
+    let nice: Vec<i32> = threads.iter().filter_map(|thread| thread.nice).collect();
+    if let Some(level) = nice.first() {
+        text += format!(", nice {}", level).as_str();
+    }
+    let mut errors: Vec<&String> = threads.iter().flat_map(|thread| thread.errors.iter()).collect();
+    errors.dedup();
+    if !errors.is_empty() {
+        text += format!(" [{}]", errors.iter().map(|err| err.as_str()).collect::<Vec<_>>().join("; ")).as_str();
+    }
+    text
+}
+
+fn join(cpus: &[impl ToString]) -> String {
+    cpus.iter().map(|cpu| cpu.to_string()).collect::<Vec<_>>().join(",")
+}
+
+// The hardware threads that share a physical core with a CPU, including the CPU itself
+fn smt_siblings(cpu: usize) -> Vec<usize> {
+    read_to_string(format!("/sys/devices/system/cpu/cpu{}/topology/thread_siblings_list", cpu))
+        .ok()
+        .and_then(|list| parse_core_list(list.trim()).ok())
+        .map(|CoreList(cores)| cores)
+        .unwrap_or(vec![cpu])
+}
+
+// The CPUs this process is allowed to run on
+#[cfg(target_os = "linux")]
+fn available_cpus() -> Vec<usize> {
+    // SAFETY: cpu_set_t is a plain bit mask that is valid when zeroed and is only read after the kernel fills it
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set) != 0 {
+            return (0..num_cpus::get()).collect();
+        }
+        (0..libc::CPU_SETSIZE as usize).filter(|cpu| libc::CPU_ISSET(*cpu, &set)).collect()
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn available_cpus() -> Vec<usize> {
+    (0..num_cpus::get()).collect()
+}
+
+#[cfg(target_os = "linux")]
+fn pin_thread(cpu: usize) -> Result<(), String> {
+    // SAFETY: the mask is built locally and sched_setaffinity with pid 0 only changes the calling thread
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(format!("could not pin a thread to CPU {}: {}", cpu, std::io::Error::last_os_error()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin_thread(_cpu: usize) -> Result<(), String> {
+    Err("pinning threads to CPUs is only supported on Linux".to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn set_thread_nice(nice: i32) -> Result<(), String> {
+    // On Linux the nice level belongs to the thread, so only the hashing thread is affected
+    // SAFETY: gettid and setpriority have no memory safety requirements
+    unsafe {
+        let tid = libc::syscall(libc::SYS_gettid) as libc::id_t;
+        if libc::setpriority(libc::PRIO_PROCESS, tid, nice) != 0 {
+            return Err(format!("could not set nice {}: {}", nice, std::io::Error::last_os_error()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_thread_nice(_nice: i32) -> Result<(), String> {
+    Err("setting the nice level of the hashing threads is only supported on Linux".to_string())
+}
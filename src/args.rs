@@ -1,4 +1,14 @@
-use clap::{arg, Parser};
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::prelude::*;
+use clap::{Args, Parser, ValueEnum};
+use solana_program::pubkey::Pubkey;
+
+use crate::{
+    affinity::{parse_core_list, CoreList},
+    run_limits::parse_stop_at,
+};
 
 #[derive(Parser, Debug)]
 pub struct BalanceArgs {
@@ -20,6 +30,9 @@ pub struct BenchmarkArgs {
         default_value = "1"
     )]
     pub threads: u64,
+
+    #[command(flatten)]
+    pub placement: CpuPlacementArgs,
 }
 
 #[derive(Parser, Debug)]
@@ -56,7 +69,7 @@ pub struct PauseArgs {}
 #[derive(Parser, Debug)]
 pub struct InitializeArgs {}
 
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 pub struct MineArgs {
     // #[cfg(not(feature = "gpu"))]
     #[arg(
@@ -68,6 +81,14 @@ pub struct MineArgs {
     )]
     pub threads: u64,
 
+    #[arg(
+        long,
+        value_name = "KEYPAIRS",
+        value_delimiter = ',',
+        help = "Mine with several wallets in this process, splitting the threads between them. A comma separated list of keypair paths, each optionally prefixed with NAME="
+    )]
+    pub keypairs: Vec<String>,
+
     #[arg(
         long,
         short,
@@ -76,6 +97,178 @@ pub struct MineArgs {
         default_value = "5"
     )]
     pub buffer_time: u64,
+
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        help = "The most seconds to keep hashing past the cutoff when the desired difficulty has not been reached",
+        default_value = "10"
+    )]
+    pub max_overtime: u64,
+
+    #[arg(
+        long,
+        value_name = "ATTEMPTS",
+        help = "How many times to resubmit the same solution if its transaction fails and the challenge has not changed",
+        default_value = "2"
+    )]
+    pub resubmit_attempts: u32,
+
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        help = "Raise the priority fee by this percentage of the configured fee for each resubmission",
+        default_value = "50"
+    )]
+    pub resubmit_fee_increase: u64,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "POLICY",
+        help = "When to reset the epoch if it is due: never, always or probabilistic at --reset-rate",
+        default_value = "probabilistic"
+    )]
+    pub reset_policy: ResetPolicy,
+
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        help = "The chance of resetting a due epoch with the probabilistic reset policy",
+        default_value = "1"
+    )]
+    pub reset_rate: f64,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "STRATEGY",
+        help = "How to choose the bus for each mine transaction: richest, weighted or round-robin",
+        default_value = "richest"
+    )]
+    pub bus_strategy: BusStrategy,
+
+    #[arg(
+        long,
+        value_name = "CELSIUS",
+        help = "Reduce the mining threads, and then pause before each pass, while the CPU is at or above this temperature"
+    )]
+    pub max_cpu_temp: Option<f32>,
+
+    #[arg(
+        long,
+        value_name = "CELSIUS",
+        help = "Restore the mining threads once the CPU has cooled to this temperature [default: 5 below --max-cpu-temp]"
+    )]
+    pub resume_cpu_temp: Option<f32>,
+
+    #[command(flatten)]
+    pub placement: CpuPlacementArgs,
+
+    #[arg(
+        long,
+        help = "Discard any saved session state for this wallet and start a new mining session",
+        default_value = "false"
+    )]
+    pub new_session: bool,
+
+    #[arg(
+        long,
+        value_name = "PASSES",
+        help = "Stop mining after this many passes"
+    )]
+    pub max_passes: Option<u32>,
+
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = humantime::parse_duration,
+        help = "Stop mining after this long, e.g. 8h or 90m"
+    )]
+    pub max_duration: Option<Duration>,
+
+    #[arg(
+        long,
+        value_name = "TIME",
+        value_parser = parse_stop_at,
+        help = "Stop mining at this local time, as HH:MM or YYYY-MM-DD HH:MM"
+    )]
+    pub stop_at: Option<DateTime<Local>>,
+
+    #[arg(
+        long,
+        value_name = "AMOUNT",
+        help = "Stop mining once this much ORE has been mined during the session"
+    )]
+    pub ore_target: Option<f64>,
+
+    #[arg(
+        long,
+        value_name = "AMOUNT",
+        help = "Automatically claim the staked ORE once it reaches this amount"
+    )]
+    pub auto_claim_threshold: Option<f64>,
+
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = humantime::parse_duration,
+        help = "Automatically claim the staked ORE this long after the last claim, e.g. 24h"
+    )]
+    pub auto_claim_every: Option<Duration>,
+
+    #[arg(
+        long,
+        alias = "to",
+        value_name = "WALLET_ADDRESS",
+        value_parser = Pubkey::from_str,
+        help = "Wallet to receive automatically claimed tokens. Defaults to the miner's wallet."
+    )]
+    pub claim_to: Option<Pubkey>,
+}
+
+// Where the hashing threads run and at what priority, shared by the mine, benchmark and service commands
+#[derive(Args, Debug, Clone)]
+pub struct CpuPlacementArgs {
+    #[arg(
+        long,
+        value_name = "CORES",
+        value_parser = parse_core_list,
+        help = "Pin each hashing thread to one of these CPUs in turn, e.g. 0-3,6"
+    )]
+    pub cpu_cores: Option<CoreList>,
+
+    #[arg(
+        long,
+        help = "Only use the first hardware thread of each physical core when pinning the hashing threads",
+        default_value = "false"
+    )]
+    pub skip_smt: bool,
+
+    #[arg(
+        long,
+        value_name = "LEVEL",
+        value_parser = clap::value_parser!(i32).range(-20..=19),
+        allow_negative_numbers = true,
+        help = "Run the hashing threads at this nice level, e.g. 10 to keep the computer responsive while mining"
+    )]
+    pub nice: Option<i32>,
+}
+
+// When a mine transaction should also reset the epoch
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResetPolicy {
+    Never,
+    Always,
+    Probabilistic,
+}
+
+// How to choose the bus for each mine transaction
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BusStrategy {
+    Richest,        // The bus with the most rewards left, which every other miner is also likely to pick
+    Weighted,       // A random bus that can pay the reward, weighted by the rewards it has left
+    RoundRobin,     // Each bus that can pay the reward in turn
 }
 
 #[derive(Parser, Debug)]
@@ -98,6 +291,12 @@ pub struct StakeArgs {
     pub sender: Option<String>,
 }
 
+#[derive(Parser, Debug)]
+pub struct ServiceArgs {
+    #[command(flatten)]
+    pub placement: CpuPlacementArgs,
+}
+
 #[cfg(feature = "admin")]
 #[derive(Parser, Debug)]
 pub struct UpdateAdminArgs {
@@ -1,62 +1,61 @@
-use std::{sync::Arc, time::Instant};
+use std::time::Duration;
 
+use colored::*;
 use solana_rpc_client::spinner;
 
-use crate::{args::BenchmarkArgs, Miner};
+use crate::{
+    affinity::CpuPlacement,
+    args::BenchmarkArgs,
+    hasher::{HashJob, HashPool, HashProgress},
+    Miner,
+};
 
-const TEST_DURATION: i64 = 30;
+const TEST_DURATION: u64 = 30;
 
 impl Miner {
     pub async fn benchmark(&self, args: BenchmarkArgs) {
         // Check num threads
         self.check_num_cores(args.threads);
 
-        // Dispatch job to each thread
-        let challenge = [0; 32];
-        let progress_bar = Arc::new(spinner::new_progress_bar());
+        let placement = match CpuPlacement::new(&args.placement) {
+            Ok(placement) => placement,
+            Err(err) => {
+                eprintln!("{} {}", "ERROR: Invalid CPU placement:".bold().red(), err);
+                std::process::exit(1);
+            }
+        };
+        let hash_pool = HashPool::new(args.threads, placement);
+        println!("Hashing: {}", hash_pool.describe_placement());
+
+        // Hash with the same engine as the mine command for the length of the test
+        let progress_bar = spinner::new_progress_bar();
         progress_bar.set_message(format!(
             "Benchmarking. This will take {} sec...",
             TEST_DURATION
         ));
-        let handles: Vec<_> = (0..args.threads)
-            .map(|i| {
-                std::thread::spawn({
-                    move || {
-                        let timer = Instant::now();
-                        let first_nonce = u64::MAX.saturating_div(args.threads).saturating_mul(i);
-                        let mut nonce = first_nonce;
-                        loop {
-                            // Create hash
-                            let _hx = drillx::hash(&challenge, &nonce.to_le_bytes());
-
-                            // Increment nonce
-                            nonce += 1;
-
-                            // Exit if time has elapsed
-                            if (timer.elapsed().as_secs() as i64).ge(&TEST_DURATION) {
-                                break;
-                            }
-                        }
-
-                        // Return hash count
-                        nonce - first_nonce
-                    }
-                })
-            })
-            .collect();
-
-        // Join handles and return best nonce
-        let mut total_nonces = 0;
-        for h in handles {
-            if let Ok(count) = h.join() {
-                total_nonces += count;
-            }
-        }
-
-        // Update log
+        let job = HashJob::new([0; 32], args.threads);
+        let result = hash_pool.find_hash_par(
+            &job,
+            |progress: &HashProgress| progress.elapsed >= Duration::from_secs(TEST_DURATION),
+            |progress: &HashProgress| {
+                progress_bar.set_message(format!(
+                    "Benchmarking. {} sec to go...   Hashes: {}   Difficulty checks: {}",
+                    TEST_DURATION.saturating_sub(progress.elapsed.as_secs()),
+                    progress.hashes,
+                    progress.checks,
+                ))
+            },
+        );
+
+        // Update log. An even spread between the slowest and fastest thread shows that the threads are not
+        // holding each other up.
+        let (slowest, fastest) = result.thread_hash_rate_range();
         progress_bar.finish_with_message(format!(
-            "Hashpower: {} H/sec",
-            total_nonces.saturating_div(TEST_DURATION as u64),
+            "Hashpower: {:.0} H/sec   Difficulty checks: {:.0}/sec   Per thread: {:.1} - {:.1} H/sec",
+            result.hash_rate(),
+            result.check_rate(),
+            slowest,
+            fastest,
         ));
     }
 }
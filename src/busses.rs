@@ -1,55 +1,151 @@
+use colored::*;
 use ore_api::{
     consts::{BUS_ADDRESSES, TOKEN_DECIMALS},
     state::Bus,
 };
 use ore_utils::AccountDeserialize;
+use rand::{distributions::WeightedIndex, prelude::Distribution, seq::SliceRandom};
 
-use crate::Miner;
+use crate::{args::BusStrategy, Miner};
 
 use solana_program::pubkey::Pubkey;
 
-impl Miner {
-    pub async fn busses(&self) -> (Pubkey, f64) {
-        let client = self.rpc_client.clone();
-        let mut max_rewards = 0.0;
-        let mut max_bus = BUS_ADDRESSES[0];
+// A bus that could be fetched and parsed, along with its address
+#[derive(Clone, Copy)]
+pub struct BusInfo {
+    pub address: Pubkey,
+    pub bus: Bus,
+}
+
+impl BusInfo {
+    pub fn rewards(&self) -> f64 {
+        (self.bus.rewards as f64) / 10f64.powf(TOKEN_DECIMALS as f64)
+    }
+}
+
+// The bus a mine transaction was sent to and why it was chosen, for the pass log
+pub struct BusChoice {
+    pub address: Pubkey,
+    pub reason: String,
+}
 
-        for address in BUS_ADDRESSES.iter() {
-            let data = client.get_account_data(address).await.unwrap();
-            match Bus::try_from_bytes(&data) {
-                Ok(bus) => {
-                    let rewards = (bus.rewards as f64) / 10f64.powf(TOKEN_DECIMALS as f64);
-                    println!("Bus {}: {:} ORE", bus.id, rewards);
-                    if rewards > max_rewards {
-                        max_rewards = rewards;
-                        max_bus = *address;
+// Chooses the bus for each mine transaction. Sending every miner to the richest bus makes them compete for
+// the same write lock, so the weighted and round-robin strategies spread the transactions across the busses.
+pub struct BusSelector {
+    pub strategy: BusStrategy,
+    next: usize,                    // The next bus index for the round-robin strategy
+}
+
+impl BusSelector {
+    pub fn new(strategy: BusStrategy) -> Self {
+        Self {
+            strategy,
+            next: rand::random::<usize>() % BUS_ADDRESSES.len(),
+        }
+    }
+
+    // Choose a bus from those that were fetched. expected_reward is the reward of the hash being submitted in grains.
+    pub fn select(&mut self, busses: &[BusInfo], expected_reward: u64) -> BusChoice {
+        if busses.is_empty() {
+            // Nothing could be fetched so send it to any bus rather than not at all
+            let address = *BUS_ADDRESSES.choose(&mut rand::thread_rng()).unwrap();
+            return BusChoice {
+                address,
+                reason: "busses could not be fetched, chosen at random".to_string(),
+            };
+        }
+
+        let richest = busses.iter().max_by_key(|b| b.bus.rewards).unwrap();
+        let funded: Vec<&BusInfo> = busses.iter().filter(|b| b.bus.rewards >= expected_reward).collect();
+        match self.strategy {
+            BusStrategy::Richest => BusChoice {
+                address: richest.address,
+                reason: format!("richest bus {} with {:.2} ORE left", richest.bus.id, richest.rewards()),
+            },
+            BusStrategy::Weighted => {
+                let chosen = WeightedIndex::new(funded.iter().map(|b| b.bus.rewards))
+                    .ok()
+                    .map(|weights| funded[weights.sample(&mut rand::thread_rng())]);
+                match chosen {
+                    Some(bus) => BusChoice {
+                        address: bus.address,
+                        reason: format!("bus {} with {:.2} ORE left, weighted from {} busses that can pay the reward",
+                            bus.bus.id,
+                            bus.rewards(),
+                            funded.len(),
+                        ),
+                    },
+                    None => BusChoice {
+                        address: richest.address,
+                        reason: format!("no bus can pay the reward, richest bus {} with {:.2} ORE left", richest.bus.id, richest.rewards()),
+                    },
+                }
+            }
+            BusStrategy::RoundRobin => {
+                // Take the next bus in turn, skipping any that cannot pay the reward
+                for _ in 0..BUS_ADDRESSES.len() {
+                    let address = BUS_ADDRESSES[self.next % BUS_ADDRESSES.len()];
+                    self.next = self.next.wrapping_add(1);
+                    if let Some(bus) = funded.iter().find(|b| b.address == address) {
+                        return BusChoice {
+                            address,
+                            reason: format!("bus {} in turn with {:.2} ORE left", bus.bus.id, bus.rewards()),
+                        };
                     }
                 }
-                Err(_) => {}
+                BusChoice {
+                    address: richest.address,
+                    reason: format!("no bus can pay the reward, richest bus {} with {:.2} ORE left", richest.bus.id, richest.rewards()),
+                }
             }
         }
-
-        (max_bus, max_rewards)
     }
+}
 
-
-    pub async fn get_best_bus(&self) -> Pubkey {
-        let client = self.rpc_client.clone();
+impl Miner {
+    pub async fn busses(&self) -> (Pubkey, f64) {
+        let busses = self.fetch_busses().await;
         let mut max_rewards = 0.0;
         let mut max_bus = BUS_ADDRESSES[0];
 
-        for address in BUS_ADDRESSES.iter() {
-            let data = client.get_account_data(address).await.unwrap();
-            if let Ok(bus) = Bus::try_from_bytes(&data) {
-                let rewards = (bus.rewards as f64) / 10f64.powf(TOKEN_DECIMALS as f64);
-                if rewards > max_rewards {
-                    max_rewards = rewards;
-                    max_bus = *address;
-                }
+        for info in busses.iter() {
+            let rewards = info.rewards();
+            println!("Bus {}: {:} ORE", info.bus.id, rewards);
+            if rewards > max_rewards {
+                max_rewards = rewards;
+                max_bus = info.address;
             }
         }
 
-        max_bus
+        (max_bus, max_rewards)
     }
 
-}
\ No newline at end of file
+    // Fetch all of the busses in a single request. A bus that cannot be fetched or parsed is skipped.
+    pub async fn fetch_busses(&self) -> Vec<BusInfo> {
+        let accounts = match self.rpc_client.get_multiple_accounts(&BUS_ADDRESSES).await {
+            Ok(accounts) => accounts,
+            Err(err) => {
+                println!("{} {}", "ERROR: Failed to fetch the busses:".bold().red(), err);
+                return vec![];
+            }
+        };
+
+        BUS_ADDRESSES
+            .iter()
+            .zip(accounts)
+            .filter_map(|(address, account)| {
+                let account = account?;
+                let bus = Bus::try_from_bytes(&account.data).ok()?;
+                Some(BusInfo {
+                    address: *address,
+                    bus: *bus,
+                })
+            })
+            .collect()
+    }
+
+    pub async fn get_best_bus(&self, selector: &mut BusSelector, expected_reward: u64) -> BusChoice {
+        let busses = self.fetch_busses().await;
+        selector.select(&busses, expected_reward)
+    }
+}
@@ -1,52 +1,59 @@
 use std::str::FromStr;
+use std::time::Duration;
 
+use chrono::prelude::*;
 use colored::*;
-use ore_api::consts::MINT_ADDRESS;
-use solana_program::pubkey::Pubkey;
+use ore_api::{consts::MINT_ADDRESS, state::Proof};
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
 use solana_sdk::signature::Signer;
 use spl_token::amount_to_ui_amount;
 
 use crate::{
-    args::ClaimArgs,
+    args::{ClaimArgs, MineArgs},
     cu_limits::CU_LIMIT_CLAIM,
-    send_and_confirm::ComputeBudget,
-    utils::{amount_f64_to_u64, ask_confirm, get_proof_with_authority},
+    send_and_confirm::{ComputeBudget, TransactionFees},
+    session::{ClaimEvent, MiningSession},
+    utils::{amount_f64_to_u64, ask_confirm, get_landed_transaction_meta, get_proof_with_authority},
     Miner,
 };
 
+// When `ore mine` should claim the staked ORE in the proof without being asked
+pub struct AutoClaim {
+    pub threshold: Option<u64>,     // Claim once the proof balance reaches this many grains
+    pub every: Option<Duration>,    // Claim when this long has passed since the last claim of the session
+    pub to: Option<Pubkey>,         // The wallet to claim to, otherwise the miner's own token account
+}
+
+impl AutoClaim {
+    pub fn new(args: &MineArgs) -> Self {
+        Self {
+            threshold: args.auto_claim_threshold.map(amount_f64_to_u64),
+            every: args.auto_claim_every,
+            to: args.claim_to,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.threshold.is_some() || self.every.is_some()
+    }
+
+    pub fn due(&self, proof: &Proof, session: &MiningSession) -> bool {
+        if proof.balance == 0 {
+            return false;
+        }
+        if self.threshold.is_some_and(|threshold| proof.balance >= threshold) {
+            return true;
+        }
+        let last_claim_at = session.claims.last().map_or(session.started_at, |claim| claim.at);
+        self.every.is_some_and(|every| Local::now().timestamp().saturating_sub(last_claim_at) >= every.as_secs() as i64)
+    }
+}
+
 impl Miner {
     pub async fn claim(&self, args: ClaimArgs) {
         let signer = self.signer();
         let pubkey = signer.pubkey();
         let proof = get_proof_with_authority(&self.rpc_client, pubkey).await;
-        let mut ixs = vec![];
-        let beneficiary = match args.to {
-            Some(to) => {
-                // Create beneficiary token account, if needed
-                let wallet = Pubkey::from_str(&to).expect("Failed to parse wallet address");
-                let benefiary_tokens = spl_associated_token_account::get_associated_token_address(
-                    &wallet,
-                    &MINT_ADDRESS,
-                );
-                if self
-                    .rpc_client
-                    .get_token_account(&benefiary_tokens)
-                    .await
-                    .is_err()
-                {
-                    ixs.push(
-                        spl_associated_token_account::instruction::create_associated_token_account(
-                            &signer.pubkey(),
-                            &wallet,
-                            &ore_api::consts::MINT_ADDRESS,
-                            &spl_token::id(),
-                        ),
-                    );
-                }
-                benefiary_tokens
-            }
-            None => self.initialize_ata().await,
-        };
 
         // Parse amount to claim
         let amount = if let Some(amount) = args.amount {
@@ -54,6 +61,8 @@ impl Miner {
         } else {
             proof.balance
         };
+        let to = args.to.map(|to| Pubkey::from_str(&to).expect("Failed to parse wallet address"));
+        let ixs = self.claim_instructions(to, amount).await;
 
         // Confirm user wants to claim
         if !ask_confirm(
@@ -71,12 +80,79 @@ impl Miner {
         }
 
         // Send and confirm
-        ixs.push(ore_api::instruction::claim(pubkey, beneficiary, amount));
         self.send_and_confirm(&ixs, ComputeBudget::Fixed(CU_LIMIT_CLAIM), false, false)
             .await
             .ok();
     }
 
+    // Claim the whole proof balance while mining, without asking for confirmation. The SOL balance is checked
+    // by the mining loop, a claim that cannot pay its fee fails instead of stopping the miner. A failed claim
+    // returns the fees it paid.
+    pub async fn auto_claim(&self, proof: &Proof, auto_claim: &AutoClaim) -> Result<ClaimEvent, TransactionFees> {
+        let amount = proof.balance;
+        let ixs = self.claim_instructions(auto_claim.to, amount).await;
+        let (result, signatures) = self
+            .send_and_confirm_tracked(&ixs, ComputeBudget::Fixed(CU_LIMIT_CLAIM), false, true)
+            .await;
+
+        // The fees are paid whether or not the claim succeeded
+        let mut fees = TransactionFees::default();
+        for sig in signatures.iter() {
+            if let Some(meta) = get_landed_transaction_meta(&self.rpc_client, sig).await {
+                fees.add(&meta);
+            }
+        }
+
+        match result {
+            Ok(sig) => Ok(ClaimEvent {
+                at: Local::now().timestamp(),
+                amount,
+                to: auto_claim.to.unwrap_or(self.signer().pubkey()).to_string(),
+                signature: sig.to_string(),
+                fees,
+            }),
+            Err(err) => {
+                eprintln!("{} {}", "Auto claim failed:".yellow(), err);
+                Err(fees)
+            }
+        }
+    }
+
+    // The instructions to claim an amount to a wallet, creating its token account if needed
+    async fn claim_instructions(&self, to: Option<Pubkey>, amount: u64) -> Vec<Instruction> {
+        let signer = self.signer();
+        let pubkey = signer.pubkey();
+        let mut ixs = vec![];
+        let beneficiary = match to {
+            Some(wallet) => {
+                // Create beneficiary token account, if needed
+                let benefiary_tokens = spl_associated_token_account::get_associated_token_address(
+                    &wallet,
+                    &MINT_ADDRESS,
+                );
+                if self
+                    .rpc_client
+                    .get_token_account(&benefiary_tokens)
+                    .await
+                    .is_err()
+                {
+                    ixs.push(
+                        spl_associated_token_account::instruction::create_associated_token_account(
+                            &signer.pubkey(),
+                            &wallet,
+                            &ore_api::consts::MINT_ADDRESS,
+                            &spl_token::id(),
+                        ),
+                    );
+                }
+                benefiary_tokens
+            }
+            None => self.initialize_ata().await,
+        };
+        ixs.push(ore_api::instruction::claim(pubkey, beneficiary, amount));
+        ixs
+    }
+
     async fn initialize_ata(&self) -> Pubkey {
         // Initialize client.
         let signer = self.signer();
@@ -0,0 +1,251 @@
+use std::env;
+
+use ore_api::{
+    consts::{BUS_EPOCH_REWARDS, ONE_MINUTE},
+    state::Config,
+};
+
+use crate::{session::MiningSession, utils::amount_u64_to_f64};
+
+// The difficulty used when none has been configured
+const DEFAULT_DIFFICULTY: u32 = 13;
+
+// The number of solves needed before the percentile strategy trusts the session history
+const MIN_PERCENTILE_SOLVES: usize = 10;
+
+// What a strategy can base its decision on at the start of a pass
+pub struct DifficultyInputs<'a> {
+    pub config: &'a Config,
+    pub session: &'a MiningSession,
+    pub cutoff_time: u64,
+}
+
+// The difficulty chosen for a pass along with why it was chosen
+pub struct DifficultyDecision {
+    pub difficulty: u32,
+    pub reason: String,
+}
+
+// Chooses the difficulty a pass aims for. A pass that has reached the target difficulty by the cutoff submits
+// straight away, otherwise the OvertimePolicy decides whether to keep hashing past the cutoff.
+pub trait DifficultyStrategy: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn decide(&self, inputs: &DifficultyInputs) -> DifficultyDecision;
+}
+
+// Always aim for the same difficulty
+pub struct StaticDifficulty {
+    pub difficulty: u32,
+}
+
+impl DifficultyStrategy for StaticDifficulty {
+    fn name(&self) -> &'static str {
+        "static"
+    }
+
+    fn decide(&self, _inputs: &DifficultyInputs) -> DifficultyDecision {
+        DifficultyDecision {
+            difficulty: self.difficulty,
+            reason: "configured difficulty".to_string(),
+        }
+    }
+}
+
+// Aim for the first difficulty that is rewarded with more than 1 ORE
+pub struct OneOreDifficulty;
+
+impl DifficultyStrategy for OneOreDifficulty {
+    fn name(&self) -> &'static str {
+        "one-ore"
+    }
+
+    fn decide(&self, inputs: &DifficultyInputs) -> DifficultyDecision {
+        let difficulty = one_ore_difficulty(inputs.config);
+        DifficultyDecision {
+            difficulty,
+            reason: format!("first difficulty rewarded over 1 ORE at base rate {:.11} ORE",
+                amount_u64_to_f64(inputs.config.base_reward_rate),
+            ),
+        }
+    }
+}
+
+// Aim for a percentile of the difficulties this wallet has solved during the session
+pub struct PercentileDifficulty {
+    pub percentile: f64,
+    pub fallback: u32,
+}
+
+impl DifficultyStrategy for PercentileDifficulty {
+    fn name(&self) -> &'static str {
+        "percentile"
+    }
+
+    fn decide(&self, inputs: &DifficultyInputs) -> DifficultyDecision {
+        let solves: usize = inputs.session.difficulties_solved.values().sum();
+        if solves < MIN_PERCENTILE_SOLVES {
+            return DifficultyDecision {
+                difficulty: self.fallback,
+                reason: format!("only {} solves recorded, using fallback until {}", solves, MIN_PERCENTILE_SOLVES),
+            };
+        }
+        let difficulty = inputs.session
+            .difficulty_distribution()
+            .iter()
+            .find(|share| share.cumulative >= self.percentile)
+            .map(|share| share.difficulty)
+            .unwrap_or(self.fallback);
+        DifficultyDecision {
+            difficulty,
+            reason: format!("{:.0}th percentile of {} solves", self.percentile, solves),
+        }
+    }
+}
+
+// Aim for the difficulty with the highest expected reward given the measured hash rate. The chance of a pass
+// reaching difficulty d with h hashes is 1 - e^(-h / 2^d) and the reward doubles with each difficulty level.
+pub struct ExpectedValueDifficulty {
+    pub fallback: OneOreDifficulty,
+}
+
+impl DifficultyStrategy for ExpectedValueDifficulty {
+    fn name(&self) -> &'static str {
+        "expected-value"
+    }
+
+    fn decide(&self, inputs: &DifficultyInputs) -> DifficultyDecision {
+        let expected_hashes = inputs.session.hash_rate() * inputs.cutoff_time as f64;
+        if expected_hashes <= 0.0 {
+            let decision = self.fallback.decide(inputs);
+            return DifficultyDecision {
+                difficulty: decision.difficulty,
+                reason: format!("no hash rate measured yet, using {}", decision.reason),
+            };
+        }
+
+        let min_difficulty = inputs.config.min_difficulty as u32;
+        let mut best = (min_difficulty, 0.0);
+        for difficulty in min_difficulty..min_difficulty.saturating_add(32) {
+            let probability = 1.0 - (-expected_hashes / 2f64.powi(difficulty as i32)).exp();
+            let expected_reward = probability * amount_u64_to_f64(reward_at_difficulty(inputs.config, difficulty));
+            if expected_reward > best.1 {
+                best = (difficulty, expected_reward);
+            }
+        }
+        DifficultyDecision {
+            difficulty: best.0,
+            reason: format!("{:.0} hashes expected in {}s, expected reward {:.11} ORE",
+                expected_hashes,
+                inputs.cutoff_time,
+                best.1,
+            ),
+        }
+    }
+}
+
+// Decides whether a pass that has not reached its target difficulty by the cutoff should keep hashing.
+// Every second past the cutoff compares the reward of submitting the best hash now with the expected reward
+// of hashing for one more second, after the liveness penalty for submitting late has been taken off both.
+pub struct OvertimePolicy {
+    pub config: Config,
+    pub liveness_secs: i64,     // Seconds of hashing after which the liveness penalty starts to reduce the reward
+    pub max_overtime: u64,      // The most seconds to keep hashing past the cutoff
+}
+
+pub enum OvertimeDecision {
+    KeepHashing,
+    Submit(String),
+}
+
+impl OvertimePolicy {
+    pub fn decide(&self, best_difficulty: u32, hash_rate: f64, elapsed_secs: u64, overtime_secs: u64) -> OvertimeDecision {
+        if overtime_secs >= self.max_overtime {
+            return OvertimeDecision::Submit(format!("maximum overtime of {}s reached", self.max_overtime));
+        }
+        let submit_now = amount_u64_to_f64(reward_at_difficulty(&self.config, best_difficulty))
+            * (1.0 - self.liveness_penalty(elapsed_secs));
+        let keep_hashing = self.expected_reward(best_difficulty, hash_rate)
+            * (1.0 - self.liveness_penalty(elapsed_secs + 1));
+        if keep_hashing > submit_now {
+            OvertimeDecision::KeepHashing
+        } else {
+            OvertimeDecision::Submit(format!("expected {:.11} ORE from another second is not more than {:.11} ORE now",
+                keep_hashing,
+                submit_now,
+            ))
+        }
+    }
+
+    // The share of the reward lost to the liveness penalty when submitting after hashing for the given time.
+    // The penalty grows linearly until no reward is paid a minute after it starts.
+    pub fn liveness_penalty(&self, elapsed_secs: u64) -> f64 {
+        let late_secs = (elapsed_secs as i64).saturating_sub(self.liveness_secs).max(0);
+        (late_secs as f64 / ONE_MINUTE as f64).min(1.0)
+    }
+
+    // The expected reward, in ORE, of the best hash after the given number of further hashes
+    fn expected_reward(&self, best_difficulty: u32, hashes: f64) -> f64 {
+        let mut expected = amount_u64_to_f64(reward_at_difficulty(&self.config, best_difficulty));
+        for difficulty in best_difficulty + 1..best_difficulty.saturating_add(32) {
+            let probability = 1.0 - (-hashes / 2f64.powi(difficulty as i32)).exp();
+            let gain = reward_at_difficulty(&self.config, difficulty)
+                .saturating_sub(reward_at_difficulty(&self.config, difficulty - 1));
+            expected += probability * amount_u64_to_f64(gain);
+        }
+        expected
+    }
+}
+
+// The reward for a hash of the given difficulty, limited to what a bus can pay out in an epoch
+pub fn reward_at_difficulty(config: &Config, difficulty: u32) -> u64 {
+    if (difficulty as u64) < config.min_difficulty {
+        return 0;
+    }
+    let normalized_difficulty = (difficulty as u64 - config.min_difficulty) as u32;
+    config.base_reward_rate
+        .saturating_mul(2u64.saturating_pow(normalized_difficulty))
+        .min(BUS_EPOCH_REWARDS)
+}
+
+// The first difficulty that is rewarded with more than 1 ORE
+pub fn one_ore_difficulty(config: &Config) -> u32 {
+    for i in 1..32u64 {
+        let reward_rate = config.base_reward_rate.saturating_mul(2u64.saturating_pow(i as u32));
+        if amount_u64_to_f64(reward_rate) > 1.0 {
+            return config.min_difficulty.saturating_add(i) as u32;
+        }
+    }
+    0
+}
+
+// Select the strategy for this miner from MINER_DIFFICULTY_STRATEGY. Without it MINER_DESIRED_DIFFICULTY_LEVEL
+// is a static difficulty or DYNAMIC for the 1 ORE rule, as before strategies were selectable.
+pub fn difficulty_strategy_from_env() -> Box<dyn DifficultyStrategy> {
+    let desired_level = env::var("MINER_DESIRED_DIFFICULTY_LEVEL").unwrap_or_default();
+    let configured_difficulty = desired_level.parse::<u32>().unwrap_or(DEFAULT_DIFFICULTY);
+    let strategy = env::var("MINER_DIFFICULTY_STRATEGY")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| match desired_level.to_uppercase().as_str() {
+            "DYNAMIC" => "one-ore".to_string(),
+            _ => "static".to_string(),
+        });
+
+    match strategy.to_lowercase().as_str() {
+        "one-ore" | "dynamic" => Box::new(OneOreDifficulty),
+        "percentile" => Box::new(PercentileDifficulty {
+            percentile: env::var("MINER_DIFFICULTY_PERCENTILE")
+                .ok()
+                .and_then(|v| v.parse::<f64>().ok())
+                .unwrap_or(50.0)
+                .clamp(0.0, 100.0),
+            fallback: configured_difficulty,
+        }),
+        "expected-value" | "ev" => Box::new(ExpectedValueDifficulty { fallback: OneOreDifficulty }),
+        "static" => Box::new(StaticDifficulty { difficulty: configured_difficulty }),
+        other => {
+            eprintln!("Warning: unknown difficulty strategy {}. Using a static difficulty of {}.", other, configured_difficulty);
+            Box::new(StaticDifficulty { difficulty: configured_difficulty })
+        }
+    }
+}
@@ -0,0 +1,345 @@
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use drillx::{equix, Hash, Solution};
+
+use crate::affinity::{describe_placement, CpuPlacement, ThreadPlacement};
+
+// How many hashes each thread computes between adding them to the shared count
+const SHARE_INTERVAL: u64 = 100;
+
+// How often the progress reporter looks at the search and asks the stop policy whether to finish
+const REPORT_INTERVAL: Duration = Duration::from_millis(100);
+
+// A search for the most difficult hash of a challenge. This is the one hashing engine used by the mine,
+// benchmark and service commands, so that a benchmark measures exactly what mining does.
+pub struct HashJob {
+    pub challenge: [u8; 32],
+    pub nonces: Range<u64>,         // Split evenly between the threads, each searching its part in order
+    pub threads: u64,
+}
+
+impl HashJob {
+    // Search the whole nonce space
+    pub fn new(challenge: [u8; 32], threads: u64) -> Self {
+        Self {
+            challenge,
+            nonces: 0..u64::MAX,
+            threads: threads.max(1),
+        }
+    }
+
+    // The part of the nonce range searched by a thread. The last thread also takes any remainder.
+    fn thread_nonces(&self, thread: u64) -> Range<u64> {
+        let size = (self.nonces.end.saturating_sub(self.nonces.start)) / self.threads;
+        let start = self.nonces.start + size * thread;
+        let end = if thread + 1 == self.threads { self.nonces.end } else { start + size };
+        start..end
+    }
+}
+
+// How a search is going, given to the stop policy and the progress callback
+#[derive(Clone, Copy, Default)]
+pub struct HashProgress {
+    pub elapsed: Duration,
+    pub hashes: u64,
+    pub checks: u64,                // The difficulty of every solution of every nonce is checked
+    pub best_difficulty: u32,
+    pub best_difficulty_secs: u64,  // How long into the search the best difficulty was found
+}
+
+impl HashProgress {
+    pub fn hash_rate(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 { self.hashes as f64 / secs } else { 0.0 }
+    }
+}
+
+// The most difficult hash found by a search, with how much hashing it took
+#[derive(Default)]
+pub struct HashResult {
+    pub nonce: u64,
+    pub difficulty: u32,
+    pub hash: Hash,
+    pub hashes: u64,
+    pub checks: u64,
+    pub thread_hash_rates: Vec<f64>, // The hashes per second of each thread
+    pub elapsed: Duration,
+    pub best_difficulty_secs: u64,
+}
+
+impl HashResult {
+    pub fn solution(&self) -> Solution {
+        Solution::new(self.hash.d, self.nonce.to_le_bytes())
+    }
+
+    pub fn hash_rate(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 { self.hashes as f64 / secs } else { 0.0 }
+    }
+
+    // The difficulty checks per second, more than the hash rate as a nonce can have several solutions
+    pub fn check_rate(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 { self.checks as f64 / secs } else { 0.0 }
+    }
+
+    // The slowest and fastest hash rate of a single thread
+    pub fn thread_hash_rate_range(&self) -> (f64, f64) {
+        let min = self.thread_hash_rates.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = self.thread_hash_rates.iter().copied().fold(0.0, f64::max);
+        if self.thread_hash_rates.is_empty() { (0.0, 0.0) } else { (min, max) }
+    }
+}
+
+// The state shared by the hashing threads during a search. Each thread keeps its own best hash and only touches
+// these when it finds a new best and every SHARE_INTERVAL hashes, so no thread ever waits on a lock.
+#[derive(Default)]
+struct SharedSearch {
+    hashes: AtomicU64,
+    checks: AtomicU64,
+    best_difficulty: AtomicU32,
+    best_difficulty_secs: AtomicU64,
+    stopped: AtomicBool,
+}
+
+impl SharedSearch {
+    fn progress(&self, timer: &Instant) -> HashProgress {
+        HashProgress {
+            elapsed: timer.elapsed(),
+            hashes: self.hashes.load(Ordering::Relaxed),
+            checks: self.checks.load(Ordering::Relaxed),
+            best_difficulty: self.best_difficulty.load(Ordering::Relaxed),
+            best_difficulty_secs: self.best_difficulty_secs.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// One worker's share of a search
+struct WorkerTask {
+    challenge: [u8; 32],
+    nonces: Range<u64>,
+    shared: Arc<SharedSearch>,
+    timer: Instant,
+    results: Sender<WorkerResult>,
+}
+
+// The best hash a worker found in its share of a search
+struct WorkerResult {
+    nonce: u64,
+    difficulty: u32,
+    hash: Hash,
+    hashes: u64,
+    elapsed: Duration,
+}
+
+// A hashing thread that lives as long as the pool, keeping its solver memory between searches
+struct Worker {
+    tasks: Sender<WorkerTask>,
+    handle: JoinHandle<()>,
+    placement: ThreadPlacement,     // The CPU and priority the worker runs at
+}
+
+impl Worker {
+    fn spawn(index: usize, placement: &CpuPlacement) -> Self {
+        let (tasks, receiver) = channel::<WorkerTask>();
+        let (placed, placed_receiver) = channel::<ThreadPlacement>();
+        let placement = placement.clone();
+        let handle = std::thread::Builder::new()
+            .name(format!("hasher-{}", index))
+            .spawn(move || {
+                let _ = placed.send(placement.apply(index));
+                let mut memory = equix::SolverMemory::new();
+                // The worker ends when the pool drops its end of the channel
+                for task in receiver {
+                    let result = search(&mut memory, &task);
+                    let _ = task.results.send(result);
+                }
+            })
+            .expect("Failed to start a hashing thread");
+        Self {
+            tasks,
+            handle,
+            placement: placed_receiver.recv().unwrap_or_default(),
+        }
+    }
+}
+
+// Hash a worker's share of the nonces until the nonces run out or the search is stopped
+fn search(memory: &mut equix::SolverMemory, task: &WorkerTask) -> WorkerResult {
+    let shared = &task.shared;
+    let mut best = WorkerResult {
+        nonce: task.nonces.start,
+        difficulty: 0,
+        hash: Hash::default(),
+        hashes: 0,
+        elapsed: Duration::ZERO,
+    };
+
+    let mut unshared = 0;               // Hashes not yet added to the shared count
+    let mut unshared_checks = 0;
+    for nonce in task.nonces.clone() {
+        for hx in nonce_hashes(memory, &task.challenge, nonce.to_le_bytes()) {
+            unshared_checks += 1;
+            let difficulty = hx.difficulty();
+            if difficulty > best.difficulty {
+                best.nonce = nonce;
+                best.difficulty = difficulty;
+                best.hash = hx;
+                if shared.best_difficulty.fetch_max(difficulty, Ordering::Relaxed) < difficulty {
+                    shared.best_difficulty_secs.store(task.timer.elapsed().as_secs(), Ordering::Relaxed);
+                }
+            }
+        }
+        best.hashes += 1;
+        unshared += 1;
+
+        if unshared == SHARE_INTERVAL {
+            shared.hashes.fetch_add(unshared, Ordering::Relaxed);
+            shared.checks.fetch_add(unshared_checks, Ordering::Relaxed);
+            unshared = 0;
+            unshared_checks = 0;
+        }
+        // A relaxed load costs nothing next to a hash, so a stop is noticed straight away even on slow machines
+        if shared.stopped.load(Ordering::Relaxed) {
+            break;
+        }
+    }
+
+    shared.hashes.fetch_add(unshared, Ordering::Relaxed);
+    shared.checks.fetch_add(unshared_checks, Ordering::Relaxed);
+    best.elapsed = task.timer.elapsed();
+    best
+}
+
+// The drillx hash of every solution equix finds for a nonce. drillx::hash_with_memory only keeps the first,
+// but each one is a valid solution on chain, so checking the rest gives more chances at a high difficulty
+// for the same solve.
+fn nonce_hashes(memory: &mut equix::SolverMemory, challenge: &[u8; 32], nonce: [u8; 8]) -> impl Iterator<Item = Hash> {
+    let solutions = equix::EquiXBuilder::new()
+        .runtime(equix::RuntimeOption::TryCompile)
+        .build(&drillx::seed(challenge, &nonce))
+        .map(|equix| equix.solve_with_memory(memory))
+        .unwrap_or_default();
+    solutions
+        .into_iter()
+        .map(move |solution| Solution::new(solution.to_bytes(), nonce).to_hash())
+}
+
+// A set of hashing threads created once and reused for every search, so that no search pays for starting
+// threads or allocating solver memory. The pool grows or shrinks to the threads each search asks for.
+pub struct HashPool {
+    workers: Mutex<Vec<Worker>>,
+    placement: CpuPlacement,        // Where new workers run
+}
+
+impl HashPool {
+    pub fn new(threads: u64, placement: CpuPlacement) -> Self {
+        let pool = Self {
+            workers: Mutex::new(vec![]),
+            placement,
+        };
+        pool.resize(threads);
+        pool
+    }
+
+    // Start or stop workers to leave the given number. A worker that is stopped finishes its current search first.
+    pub fn resize(&self, threads: u64) {
+        let mut workers = self.workers.lock().unwrap();
+        self.resize_workers(&mut workers, threads.max(1) as usize);
+    }
+
+    fn resize_workers(&self, workers: &mut Vec<Worker>, threads: usize) {
+        // Replace any worker that has died, e.g. from a panic while hashing
+        for (index, worker) in workers.iter_mut().enumerate() {
+            if worker.handle.is_finished() {
+                *worker = Worker::spawn(index, &self.placement);
+            }
+        }
+        workers.truncate(threads);
+        while workers.len() < threads {
+            workers.push(Worker::spawn(workers.len(), &self.placement));
+        }
+    }
+
+    // Where the workers actually run, for the startup banner
+    pub fn describe_placement(&self) -> String {
+        let workers = self.workers.lock().unwrap();
+        let placements: Vec<ThreadPlacement> = workers.iter().map(|worker| worker.placement.clone()).collect();
+        describe_placement(&self.placement, &placements)
+    }
+
+    // Search for the most difficult hash with one worker per share of the nonce range, resizing the pool to the
+    // job's threads first. The calling thread reports progress while the workers hash: it asks `stop` every
+    // REPORT_INTERVAL whether to finish and calls `progress` about once a second. The search ends once told to
+    // stop or the nonces run out. This blocks until every worker has finished its share.
+    pub fn find_hash_par(
+        &self,
+        job: &HashJob,
+        stop: impl Fn(&HashProgress) -> bool,
+        progress: impl Fn(&HashProgress),
+    ) -> HashResult {
+        // Holding the workers for the whole search means concurrent searches take turns
+        let mut workers = self.workers.lock().unwrap();
+        self.resize_workers(&mut workers, job.threads as usize);
+
+        let timer = Instant::now();
+        let shared = Arc::new(SharedSearch::default());
+        let (results, receiver) = channel::<WorkerResult>();
+        for (thread, worker) in workers.iter().enumerate() {
+            let _ = worker.tasks.send(WorkerTask {
+                challenge: job.challenge,
+                nonces: job.thread_nonces(thread as u64),
+                shared: shared.clone(),
+                timer,
+                results: results.clone(),
+            });
+        }
+        // Only the tasks hold senders now, so the channel closes once every worker is done with its task
+        drop(results);
+
+        // Report progress and apply the stop policy until the workers have finished
+        let mut bests = vec![];
+        let mut last_progress_secs = 0;
+        loop {
+            match receiver.recv_timeout(REPORT_INTERVAL) {
+                Ok(best) => bests.push(best),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            let snapshot = shared.progress(&timer);
+            if !shared.stopped.load(Ordering::Relaxed) && stop(&snapshot) {
+                shared.stopped.store(true, Ordering::Relaxed);
+            }
+            if snapshot.elapsed.as_secs() != last_progress_secs {
+                last_progress_secs = snapshot.elapsed.as_secs();
+                progress(&snapshot);
+            }
+        }
+
+        let mut result = HashResult {
+            hashes: shared.hashes.load(Ordering::Relaxed),
+            checks: shared.checks.load(Ordering::Relaxed),
+            thread_hash_rates: bests
+                .iter()
+                .map(|best| best.hashes as f64 / best.elapsed.as_secs_f64().max(f64::EPSILON))
+                .collect(),
+            // The search ended when the last worker finished, not when the reporter noticed
+            elapsed: bests.iter().map(|best| best.elapsed).max().unwrap_or_else(|| timer.elapsed()),
+            best_difficulty_secs: shared.best_difficulty_secs.load(Ordering::Relaxed),
+            ..Default::default()
+        };
+        for best in bests {
+            if best.difficulty > result.difficulty {
+                result.nonce = best.nonce;
+                result.difficulty = best.difficulty;
+                result.hash = best.hash;
+            }
+        }
+        result
+    }
+}
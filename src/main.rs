@@ -1,3 +1,4 @@
+mod affinity;
 mod args;
 mod balance;
 mod benchmark;
@@ -6,22 +7,34 @@ mod claim;
 mod close;
 mod config;
 mod cu_limits;
+mod difficulty;
+mod hasher;
 #[cfg(feature = "admin")]
 mod initialize;
 mod mine;
 mod miners;
 mod open;
+mod report;
 mod rewards;
 mod send_and_confirm;
+mod run_limits;
+mod scheduler;
+mod session;
+mod shutdown;
 mod stake;
+mod thermal;
+mod timetable;
 mod upgrade;
 mod utils;
+mod wallets;
 mod service;
 
 use std::sync::Arc;
 
 use args::*;
 use clap::{command, Parser, Subcommand};
+use indicatif::{ProgressBar, ProgressStyle};
+use solana_rpc_client::spinner;
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{
     commitment_config::CommitmentConfig,
@@ -31,7 +44,9 @@ use solana_sdk::{
 struct Miner {
     pub keypair_filepath: Option<String>,
     pub priority_fee: u64,
+    pub min_sol_balance: f64,
     pub rpc_client: Arc<RpcClient>,
+    pub label: String,              // Prefixed to the spinners of a wallet mined alongside others, empty otherwise
 }
 
 #[derive(Subcommand, Debug)]
@@ -74,7 +89,7 @@ enum Commands {
     Initialize(InitializeArgs),
 
     #[command(about = "Start the mining service")]
-    Service,
+    Service(ServiceArgs),
 }
 
 #[derive(Parser, Debug)]
@@ -114,6 +129,15 @@ struct Args {
     )]
     priority_fee: u64,
 
+    #[arg(
+        long,
+        value_name = "SOL",
+        help = "The SOL balance below which the wallet is too low to pay for transactions",
+        default_value = "0.005",
+        global = true
+    )]
+    min_sol_balance: f64,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -142,6 +166,7 @@ async fn main() {
     let miner = Arc::new(Miner::new(
         Arc::new(rpc_client),
         args.priority_fee,
+        args.min_sol_balance,
         Some(default_keypair),
     ));
 
@@ -170,7 +195,8 @@ async fn main() {
             miner.miners().await;
         }
         Commands::Mine(args) => {
-            miner.mine(args).await;
+            let stop_reason = miner.mine(args).await;
+            std::process::exit(stop_reason.exit_code());
         }
         Commands::Rewards(_) => {
             miner.rewards().await;
@@ -185,8 +211,8 @@ async fn main() {
         Commands::Initialize(_) => {
             miner.initialize().await;
         }
-        Commands::Service => {
-            service::start_service().await;
+        Commands::Service(args) => {
+            service::start_service(args).await;
         }
     }
 }
@@ -195,12 +221,15 @@ impl Miner {
     pub fn new(
         rpc_client: Arc<RpcClient>,
         priority_fee: u64,
+        min_sol_balance: f64,
         keypair_filepath: Option<String>,
     ) -> Self {
         Self {
             rpc_client,
             keypair_filepath,
             priority_fee,
+            min_sol_balance,
+            label: String::new(),
         }
     }
 
@@ -212,4 +241,16 @@ impl Miner {
         }
     }
 
+    // A spinner prefixed with the wallet's label, so that the spinners of wallets mined at once can be told apart
+    pub fn new_progress_bar(&self) -> ProgressBar {
+        let progress_bar = spinner::new_progress_bar();
+        progress_bar.set_style(
+            ProgressStyle::default_spinner()
+                .template("{spinner:.green} {prefix}{wide_msg}")
+                .expect("ProgressStyle::template direct input to be correct"),
+        );
+        progress_bar.set_prefix(self.label.clone());
+        progress_bar
+    }
+
 }
@@ -1,6 +1,7 @@
 use std::fs::{File, write};
 use std::io::{BufRead, BufReader, Result};
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::env;
 use std::collections::BTreeMap;
 use std::time::{Instant, Duration};
@@ -15,63 +16,41 @@ use serde_json::to_string_pretty;
 use hostname;
 use rand::Rng;
 
+use clap::ValueEnum;
 use colored::*;
-use drillx::{
-    equix::{self},
-    Hash, Solution
-};
+use drillx::Solution;
 use ore_api::{
     consts::{EPOCH_DURATION},
-    state::{Config, Proof},
+    state::Config,
 };
 
 use solana_program::{
-	pubkey::Pubkey,
-    native_token::{lamports_to_sol, sol_to_lamports},
+    native_token::lamports_to_sol,
 };
 
-use solana_rpc_client::spinner;
 use solana_sdk::signer::Signer;
-use solana_sdk::clock::Clock;
 
 use crate::{
-    args::MineArgs,
+    affinity::CpuPlacement,
+    args::{MineArgs, ResetPolicy},
+    busses::BusSelector,
+    claim::AutoClaim,
+    cu_limits::CU_LIMIT_MINE_RESET,
+    difficulty::{difficulty_strategy_from_env, reward_at_difficulty, DifficultyInputs, OvertimeDecision, OvertimePolicy},
+    hasher::{HashJob, HashPool, HashProgress},
+    run_limits::{RunLimits, StopReason},
     send_and_confirm::ComputeBudget,
-    utils::{ amount_u64_to_f64, get_clock, get_config, get_proof_with_authority, proof_pubkey},
+    shutdown::Shutdown,
+    report::{max_reward_line, session_report, strip_ansi, webhook_report_format_from_env, ReportFormat, SessionReportInputs, StatsReport},
+    thermal::{ThermalEvent, ThermalThrottle},
+    timetable::timetable_from_env,
+    wallets::{wallet_profiles, CombinedStats, WalletProfile},
+    session::{BaseRateInfo, ClaimEvent, MiningSession, PassOutcome, PendingPass, Prices, RunningCosts},
+    utils::{ amount_u64_to_f64, get_clock, get_config, get_landed_transaction_meta, get_proof_with_authority, parse_mine_event, proof_pubkey},
     Miner,
 };
 
 
-#[derive(Serialize, Clone)]
-struct BaseRateInfo {
-    rate: f64,
-    change: f64,
-    trend: char,
-}
-
-fn update_base_rate_history(history: &mut VecDeque<BaseRateInfo>, new_rate: f64) {
-    let (change, trend) = if let Some(last_rate) = history.back() {
-        let change_percentage = (new_rate - last_rate.rate) / last_rate.rate * 100.0;
-        let trend = if change_percentage > 0.0 { '▲' } else if change_percentage < 0.0 { '▼' } else { '─' };
-        (change_percentage, trend)
-    } else {
-        (0.0, '─')
-    };
-
-    history.push_back(BaseRateInfo {
-        rate: new_rate,
-        change,
-        trend,
-    });
-
-    if history.len() > 10 {
-        history.pop_front();
-    }
-}
-
-const GRAINS_PER_ORE: u64 = 100_000_000_000; // 100 billion grains per ORE
-
-
 #[derive(Serialize)]
 struct LogInfo {
 	computer_name: String,
@@ -80,72 +59,76 @@ struct LogInfo {
     passes_without_rewards: u32,
     start_time: String,
     duration: String,
+    elapsed: String,
+    elapsed_hours: f64,
+    active_mining_hours: f64,
+    ore_per_hour: f64,
+    sol_per_hour: f64,
+    hash_rate: f64,
     cpu_temp: String,
+    thermal_throttled: bool,
+    thermal_threads: u64,
+    thermal_throttle_count: u32,
+    thermal_events: VecDeque<ThermalEvent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    report: Option<String>,
     load_avg_1min: f32,
     load_avg_5min: f32,
     load_avg_15min: f32,
     current_staked_balance: f64,
     current_sol_balance: f64,
     base_reward_rate: f64,
+    last_pass_signature: String,
+    last_pass_reward_source: String,
     last_pass_ore_mined: f64,
+    last_pass_late_rewards: f64,
+    last_pass_ore_staked: f64,
+    last_pass_ore_claimed: f64,
     last_pass_sol_used: f64,
+    last_pass_base_fee: f64,
+    last_pass_priority_fee: f64,
+    last_pass_failed_fee: f64,
+    last_pass_failed_transactions: u32,
     session_ore_mined: f64,
+    session_ore_staked: f64,
+    session_ore_claimed: f64,
     session_sol_used: f64,
+    session_base_fees: f64,
+    session_priority_fees: f64,
+    session_failed_fees: f64,
+    session_failed_transactions: u32,
+    session_resets: u32,
+    session_reset_fees: f64,
+    last_pass_energy_kwh: f64,
+    last_pass_energy_cost: f64,
+    session_energy_kwh: f64,
+    session_energy_cost: f64,
+    session_cloud_cost: f64,
+    session_profit: f64,
     max_reward: f64,
     session_hashes: u64,
     difficulties_solved: BTreeMap<u32, usize>,
     base_rate_history: VecDeque<BaseRateInfo>,
+    claims: Vec<ClaimEvent>,
 }
 
-fn format_base_rate_change_table(history: &VecDeque<BaseRateInfo>) -> String {
-    let mut table = String::new();
-    table += "|------------|-------|-------|-------|-------|-------|-------|-------|-------|-------|-------|\n";
-    table += "| Pass #     |     1 |     2 |     3 |     4 |     5 |     6 |     7 |     8 |     9 |    10 |\n";
-    table += "|------------|-------|-------|-------|-------|-------|-------|-------|-------|-------|-------|\n";
-    
-    // Base Rate row (in grains)
-    table += "| Base Rate  |";
-    for info in history {
-        let grains = (info.rate * GRAINS_PER_ORE as f64).round() as u64;
-        table += &format!("{:7}|", grains);
-    }
-    table += "\n";
-
-    // Change row
-    table += "| Change     |";
-    for info in history {
-        if info.change == 0.0 {
-            table += "   -   |";
-        } else {
-            let change_str = format!("{:+6.1}%", info.change);
-            if info.change > 0.0 {
-                table += &format!("{}", change_str.green());
-            } else {
-                table += &format!("{}", change_str.red());
-            }
-            table += "|";
-        }
-    }
-    table += "\n";
-
-    // Trend row
-    table += "| Trend      |";
-    for info in history {
-        let trend_str = match info.trend {
-            '▲' => format!("   {}   ", "▲".green()),
-            '▼' => format!("   {}   ", "▼".red()),
-            _ => "   -   ".to_string(),
-        };
-        table += &format!("{}|", trend_str);
-    }
-    table += "\n";
-
-    table += "|------------|-------|-------|-------|-------|-------|-------|-------|-------|-------|-------|\n";
-    table
+// Sent to the webhook when the wallet runs out of SOL and again when it has been funded
+#[derive(Serialize)]
+struct SolBalanceAlert {
+    event: String,
+    miner_name: String,
+    computer_name: String,
+    wallet: String,
+    sol_balance: f64,
+    min_sol_balance: f64,
+    time: String,
 }
 
-
-
+// How often to check the balance of a wallet that is waiting to be funded
+const SOL_BALANCE_POLL_SECS: u64 = 10;
+// How many times to look up the SOL balance before treating it as unknown, and how long to wait in between
+const SOL_BALANCE_RETRIES: u32 = 5;
+const SOL_BALANCE_RETRY_MILLIS: u64 = 500;
 
 impl Miner {
     async fn send_log_to_webhook(webhook_url: String, json_log: String) -> std::result::Result<(), String> {
@@ -167,23 +150,30 @@ impl Miner {
         }
     }
 
-    fn calculate_optimal_difficulty(base_reward_rate: u64, base_difficulty: u64) -> u32 {
-        let mut diff_to_target = 0;
-        for i in 1..32 as u64 {
-            let reward_rate = base_reward_rate.saturating_mul(2u64.saturating_pow(i as u32));
-            if amount_u64_to_f64(reward_rate) > 1.0 && diff_to_target == 0 {
-                diff_to_target = base_difficulty.saturating_add(i) as u32;
-                break;
-            }
-        }
-        diff_to_target
-    }
-	
 
-	pub async fn mine(&self, args: MineArgs) {
-		const MIN_SOL_BALANCE: f64 = 0.005;
+	pub async fn mine(&self, args: MineArgs) -> StopReason {
+		// Finish the current pass and write the final stats when asked to stop
+		let shutdown = Shutdown::listen();
+
+        // Check num threads
+        self.check_num_cores(args.threads);
+
+		let mut profiles = match wallet_profiles(&args, self.keypair_filepath.clone()) {
+			Ok(profiles) => profiles,
+			Err(err) => {
+				eprintln!("{} {}", "ERROR: Invalid wallet:".bold().red(), err);
+				std::process::exit(1);
+			}
+		};
+		if profiles.len() > 1 {
+			return self.mine_wallets(args, profiles, shutdown).await;
+		}
+		self.mine_wallet(&args, profiles.remove(0), shutdown, None).await
+	}
 
-		let mut base_rate_history: VecDeque<BaseRateInfo> = VecDeque::with_capacity(10);
+	// The mining loop for a single wallet. When several wallets are mined at once its session is also added to
+	// the combined stats after each pass.
+	pub async fn mine_wallet(&self, args: &MineArgs, profile: WalletProfile, shutdown: Shutdown, combined: Option<Arc<CombinedStats>>) -> StopReason {
 
 		// Register, if needed.
         let signer = self.signer();
@@ -191,61 +181,58 @@ impl Miner {
 
 		let sys = System::new();
 
-        // Check num threads
-        self.check_num_cores(args.threads);
+		let run_limits = RunLimits::new(args);
+		let auto_claim = AutoClaim::new(args);
+		let mut bus_selector = BusSelector::new(args.bus_strategy);
+		let mut thermal = ThermalThrottle::new(args);
+		let mut last_threads = profile.threads;				// The threads the previous pass mined with
+		let placement = match CpuPlacement::new(&args.placement) {
+			Ok(placement) => placement.starting_at(profile.first_thread),
+			Err(err) => {
+				eprintln!("{} {}", "ERROR: Invalid CPU placement:".bold().red(), err);
+				std::process::exit(1);
+			}
+		};
+		let hash_pool = Arc::new(HashPool::new(profile.threads, placement));	// The hashing threads, kept for the whole session
+		let timetable = match timetable_from_env() {
+			Ok(timetable) => timetable,
+			Err(err) => {
+				eprintln!("{} {}", "ERROR: Invalid MINER_SCHEDULE:".bold().red(), err);
+				std::process::exit(1);
+			}
+		};
+
+		let miner_name=env::var("MINER_NAME").unwrap_or("Unnamed Miner".to_string());
+		let wallet_name=profile.name.clone();
+
+		// Restore the session for this wallet so stats survive a restart of the miner process
+		let session_key=profile.session_key.clone();
+		let session_file=profile.session_file.clone();
+		let mut session=MiningSession::load_or_new(&session_file, &session_key, args.new_session);
 
-		let mut cutoff_time: u64;						// How long each pass will mine for
-		let mining_start_time_display = Local::now();	// When the miner was initially started
-		let mining_start_time = Instant::now();	// When the miner was initially started
-		let mut pass=1;								// This represents how many times the miner has tried to mine
-		let mut current_sol_balance: f64;					// The amount of SOL in the wallet
+		let mining_start_time_display = session.started_at_local();	// When the mining session was initially started
+		let mut pass=session.next_pass();				// This represents how many times the miner has tried to mine
+		let first_pass=pass;							// The first pass of this miner process
+		let mut current_sol_balance: f64=0.0;				// The amount of SOL in the wallet, the last known amount if a lookup fails
 		let mut current_staked_balance: f64;				// The amount of staked ORE in the wallet
-		let mut last_sol_balance: f64 = 0.0;				// The amount of SOL in the wallet in the previous mining pass
-		let mut last_staked_balance: f64 = 0.0;				// The amount of ORE in the wallet in the previous mining pass
-		let mut last_pass_difficulty: u32= 0;				// The best difficulty solved in the last pass
-		let mut session_ore_mined: f64 = 0.0;				// A running tally of the ORE mined in all passes (session)
-		let mut session_sol_used: f64 = 0.0;				// A running tally of the SOL spent in all passes (session)
-		let mut session_hashes: u64 = 0;					// A running tally of the number of hashes in all passes (session)
-		let mut difficulties_solved: BTreeMap<u32, usize> = BTreeMap::new();	// An array that counts how many of each difficulty has been solved in this session
-		let mut max_reward: f64 = 0.0;						// What has been the highest reward mined in this session
-		let mut max_reward_text: String = "".to_string();	// A text string detailing the maximum reward pass
-		let mut passes_without_rewards: u32 = 0; 			// Counts how many passes have been without rewards
 
 		let mut _current_ore_price:f64;
 		let mut _current_sol_price:f64;
 
-		let mut _optimal_diff_hits = 0;
-		let miner_name=env::var("MINER_NAME").unwrap_or("Unnamed Miner".to_string());
-		let wallet_name=env::var("WALLET_NAME").unwrap_or("Unnamed Wallet".to_string());
-		let rig_cost_per_hour: f64 = env::var("CLOUD_COST_PER_HOUR").ok().and_then(|x| x.parse::<f64>().ok()).unwrap_or(1.0);
+		let running_costs = RunningCosts::from_env().share(profile.cost_share);	// This wallet's share of the rig's costs
 		let log_webhook=env::var("LOG_WEBHOOK").unwrap_or("".to_string());
-		let mut log_startup = String::new();
-        let use_dynamic_difficulty = env::var("MINER_DESIRED_DIFFICULTY_LEVEL")
-            .map(|v| v.to_uppercase() == "DYNAMIC")
-            .unwrap_or(false);
-
-        let mut rig_desired_difficulty_level: u32 = if use_dynamic_difficulty {
-            13 // Default value for dynamic mode
-        } else {
-            env::var("MINER_DESIRED_DIFFICULTY_LEVEL")
-                .ok()
-                .and_then(|v| v.parse::<u32>().ok())
-                .unwrap_or(13) // Default to 13 if parsing fails
-        };
-
-        log_startup += format!("| Using {} difficulty calculation. Initial difficulty: {}\n", 
-            if use_dynamic_difficulty { "dynamic" } else { "static" },
-            rig_desired_difficulty_level
-        ).as_str();
-
-		let stats_logfile=env::var("STATS_LOGFILE").unwrap_or("".to_string());
+		let difficulty_strategy = difficulty_strategy_from_env();	// Chooses the difficulty each pass aims for
+		let mut rig_desired_difficulty_level: u32;
+
+		let stats_logfile=profile.stats_logfile.clone();
 	
 		let separator_line = ("=======================================================================================================================================").to_string().dimmed();
 		let green_separator_line=separator_line.clone().green();
 		let yellow_separator_line=separator_line.clone().yellow();
 
 		let mut log_startup=String::from("");
-		let mut log_stats=String::from("");
+		let mut stats_report: Option<StatsReport> = None;		// The stats of the last pass that was summarised
+		let webhook_report_format = webhook_report_format_from_env();
 		let mut log_start_pass=String::from("");
 		let mut log_end_pass=String::from("");
 		let mut log_mined=String::from("");
@@ -254,30 +241,58 @@ impl Miner {
 		log_startup+=format!("{}\n", green_separator_line).as_str();
 		
 
-		let _rig_cost_per_hour: f64 = match env::var("CLOUD_COST_PER_HOUR") {
-			Ok(value) => {
-				match value.parse::<f64>() {
-					Ok(parsed) => {
-						parsed
-					},
-					Err(_e) => {
-						0.0 // Default value
-					}
-				}
-			},
-			Err(_e) => {
-				0.0 // Default value
-			}
-		};
-
-		log_startup+=format!("| Cloud cost per hour: ${:.2}\n", rig_cost_per_hour).as_str();
+		log_startup+=format!("| Cloud cost per hour: ${:.2}\n", running_costs.cloud_cost_per_hour).as_str();
+		if running_costs.wattage_idle>0.0 || running_costs.wattage_busy>0.0 {
+			log_startup+=format!("| Electricity: {}W idle / {}W busy @ ${:.2}/kWh\n",
+				running_costs.wattage_idle,
+				running_costs.wattage_busy,
+				running_costs.cost_per_kwh,
+			).as_str();
+		}
+		log_startup+=format!("| Hashing: {}\n", hash_pool.describe_placement()).as_str();
+		log_startup+=format!("| Difficulty strategy: {}\n", difficulty_strategy.name().bold()).as_str();
+		if auto_claim.enabled() {
+			log_startup+=format!("| Auto claim: {}{}to {}\n",
+				auto_claim.threshold.map(|t| format!("at {} ORE ", amount_u64_to_f64(t))).unwrap_or_default(),
+				auto_claim.every.map(|e| format!("every {} ", format_duration(e))).unwrap_or_default(),
+				auto_claim.to.unwrap_or(signer.pubkey()),
+			).as_str();
+		}
+		log_startup+=format!("| Bus strategy: {}\n", args.bus_strategy.to_possible_value().unwrap().get_name()).as_str();
+		log_startup+=format!("| Reset policy: {}\n", match args.reset_policy {
+			ResetPolicy::Never => "never".to_string(),
+			ResetPolicy::Always => "always when the epoch is due".to_string(),
+			ResetPolicy::Probabilistic => format!("{}% chance when the epoch is due", args.reset_rate),
+		}).as_str();
+		if let Some(max_temp) = thermal.max_temp {
+			log_startup+=format!("| Thermal limit: throttle at {}°C, restore at {}°C\n", max_temp, thermal.resume_temp).as_str();
+		}
+		if let Some(timetable) = &timetable {
+			log_startup+=format!("| Mining schedule: {}\n", timetable.describe()).as_str();
+		}
+		if let Some(limits) = run_limits.describe() {
+			log_startup+=format!("| Mining will stop after {}\n", limits).as_str();
+		}
 		
 		log_startup+=format!("| Wallet name: {}\n", wallet_name.bold()).as_str();
 		_current_ore_price=self.load_ore_price();
 		_current_sol_price=self.load_sol_price();
+		if session.passes>0 {
+			log_startup+=format!("| Resuming session started at {}: {} passes, {} ORE mined, {:.6} SOL used\n",
+				mining_start_time_display.format("%H:%M:%S on %Y-%m-%d"),
+				session.passes,
+				format!("{:.11}", session.session_ore_mined).green(),
+				session.session_sol_used,
+			).as_str();
+		} else {
+			log_startup+=format!("| Starting a new session for {}\n", session_key).as_str();
+		}
+		log_startup+=format!("| Session state file: {}\n", session_file).as_str();
 		log_startup+=format!("{}\n", green_separator_line).as_str();
 		log_startup+=format!("| {} {}...\n", "Starting first pass...".bold().green(), miner_name.bold().green()).as_str();
 
+		let mut last_checkpoint=Instant::now();			// When the elapsed time was last added to the session
+
         // Start mining loop
         loop {
 			let pass_start_time = Instant::now();
@@ -288,7 +303,8 @@ impl Miner {
 			// println!("Got proof....");
 
 			// Determine Wallet ORE & SOL Balances
-			current_sol_balance=self.get_sol_balance(false).await;
+			let sol_balance=self.get_sol_balance().await;		// None if the balance could not be looked up
+			current_sol_balance=sol_balance.unwrap_or(current_sol_balance);
 			current_staked_balance=amount_u64_to_f64(proof.balance);
 
 			// Lookup CPU stats for 1min, 5 mins and 15 mins
@@ -315,77 +331,48 @@ impl Miner {
 				cpu_temp_txt="".to_string();
 			}
 
-			// Calc cutoff time
-			let clock = get_clock(&self.rpc_client).await;
-           	cutoff_time = self.get_cutoff(proof, args.buffer_time, &clock).await;
-
 			// Special handling of first miner pass
-			if pass==1 {
-				let seconds_since_last_hash: u64 =clock.unix_timestamp.saturating_sub(proof.last_hash_at) as u64;
-				log_startup+=format!("| {} seconds since last hash submission.\n", seconds_since_last_hash).as_str();
-				// Classed as Spam if submitting transaction before 55 secs
-				// Classed as Liveness Penalty if submitting difficulty >60 and <=120
-				// Classed as Liveness Penalty & no Reward if >120
-				let mut extra_text="";
-				// Shorten the first pass if a liveness penalty (no reward) will be applied
-				if seconds_since_last_hash>110 {
-					log_startup+=format!("| {}\n", "You will be hit with a liveness penalty for the first pass resulting in no rewards.".red()).as_str();
-					log_startup+=format!("| Reducing it's duration to compensate and stop you crying if you solve a high difficulty.\n").as_str();
-					cutoff_time=3;
-					extra_text="shortened to ";
-				} 
-				// Notify the first pass will get a liveness penalty applied (reduced reward)
-				else if seconds_since_last_hash>60 {
-					log_startup+=format!("| {}\n", "You will be hit with a liveness penalty for the first pass resulting in a reduced reward.".yellow()).as_str();
-					cutoff_time = 60 - (seconds_since_last_hash-60) - args.buffer_time;
-					if cutoff_time>15 {	// shorten the pass to 15 to maximise reduced reward
-						cutoff_time=15;
-					}
-					if cutoff_time<3 { // catch if it would be less than 3 which will fail
-						cutoff_time=3;
-					}
-				}
-				log_startup+=format!("| First pass will be {}{} seconds long.\n", extra_text, cutoff_time).as_str();
+			if pass==first_pass {
 				log_startup+=format!("{}\n", green_separator_line).as_str();	
 
 				// Write the startup log to the screen
 				print!("{}", log_startup);
 				// Write stat to log file every pass overwriting previous version
 				if stats_logfile != "" {
-					let _result = write(stats_logfile.clone(), log_startup.clone());
+					let _result = write(stats_logfile.clone(), strip_ansi(&log_startup));
 				}
 			}
 
-			// Summarize the results of the previous mining pass
-			if pass>1 {
+			// Summarize the results of the previous mining pass. It may have been mined before the miner restarted.
+			if let Some(PendingPass { mut outcome, proof_before }) = session.pending_pass.clone() {
 				// Fetch the current config at the start of each loop
 				let config = get_config(&self.rpc_client).await;
-				// Add the difference in staked ore from the previous pass to the session_ore_mined tally
-				let mut last_pass_ore_mined=current_staked_balance-last_staked_balance;
-				// If ore has been unstaked, then this value will be wrong for last pass so ignore it
-				if last_pass_ore_mined<0.0 {
-					passes_without_rewards += 1;
-					last_pass_ore_mined=0.0;
-				}
-				// Not sure how to detect is additional ore has been staked
-				// possible to check with proof last claimed > last pass start time?
-				session_ore_mined+=last_pass_ore_mined;	// Update the session ore mined tally
-
-				// Add the difference in sol from the previous pass to the session_sol_used tally
-				let mut last_pass_sol_used=current_sol_balance-last_sol_balance;
-				// Sol has been added to wallet so disregard the last passed sol_used as it is incorrect
-				if last_pass_sol_used>0.0 {
-					last_pass_sol_used=-0.0;
-				}
-				// not sure how to detect a change in sol level after the start of the last pass that is not just a transaction fee.
-				session_sol_used-=last_pass_sol_used;	// Update the session sol used tally
+				// Settle the rewards of the previous pass and add it to the session tallies
+				session.pending_pass=None;
+				outcome.settle(&proof_before, &proof);
+				let new_max_reward = session.record_pass(&outcome);
+				session.record_elapsed(last_checkpoint.elapsed().as_secs_f64());
+				last_checkpoint=Instant::now();
+				let last_pass_ore_mined = outcome.ore_mined();
+				let last_pass_sol_used = outcome.sol_used();
 
 				log_mined+=format!("  Mined: {} ORE     Cost: {:>11.6} SOL    Session: {} ORE    {:11.6} SOL\n",
 					format!("{:>17.11}", last_pass_ore_mined).green(),
 					last_pass_sol_used,
-					format!("{:>17.11}", session_ore_mined).green(),
-					session_sol_used,
+					format!("{:>17.11}", session.session_ore_mined).green(),
+					session.session_sol_used,
 				).as_str();
+				if outcome.late_rewards>0 {
+					log_mined+=format!("  Late reward: {} ORE from an earlier submission that landed after its pass\n",
+						format!("{:>17.11}", amount_u64_to_f64(outcome.late_rewards)).green(),
+					).as_str();
+				}
+				if outcome.staked>0 {
+					log_mined+=format!("  Staked: {} ORE\n", format!("{:>17.11}", amount_u64_to_f64(outcome.staked)).bright_cyan()).as_str();
+				}
+				if outcome.claimed>0 {
+					log_mined+=format!("  Claimed: {} ORE\n", format!("{:>17.11}", amount_u64_to_f64(outcome.claimed)).bright_cyan()).as_str();
+				}
 
 				// Show a warning if you never earned anything in the last pass
 				if last_pass_ore_mined==0.0 {
@@ -396,19 +383,11 @@ impl Miner {
 					).as_str();
 				}
 				// Log if this pass is your maximum reward for this session
-				if last_pass_ore_mined>max_reward {
-					max_reward = last_pass_ore_mined;
-		       		max_reward_text = format!("|      Max session reward: {} ORE  (${:.2}) at difficulty {} during pass {}\t{}",
-						format!("{:>17.11}", last_pass_ore_mined).green(),
-						last_pass_ore_mined * _current_ore_price,
-						last_pass_difficulty.to_string().yellow(),
-						(pass-1).to_string().yellow(),
-						format!("[~{:.4}% of supply]", last_pass_ore_mined * 100.0).dimmed(),
-					);
+				if new_max_reward {
 					log_mined+=format!("{}\n{}\n{}\n{}\n", 
 						green_separator_line,
 						"| You just mined your highest reward for this session!!".bold().green(),
-						max_reward_text.green(), 
+						max_reward_line(&session, _current_ore_price).render_text(true),
 						green_separator_line,
 					).as_str();				
 				}
@@ -417,167 +396,23 @@ impl Miner {
 
 				// Show a status page of the difficulties solved for this mining session every X passes
 				// This will indicate the most common difficulty solved by this miner
-				log_stats+=format!("{}\n", green_separator_line).as_str();
-
-				// Display stats banner
-				log_stats+=format!("{}\n",
-					format!("| Stats for {} pass {} at {}\t[{}]\tStarted at {}",
-						miner_name.bold(),
-						pass-1,
-						Local::now().format("%H:%M:%S on %Y-%m-%d").to_string(),
-						wallet_name.bold(),
-						mining_start_time_display.format("%H:%M:%S on %Y-%m-%d").to_string(),
-					).green(),
-				).as_str();
-				log_stats+=format!("{}\n", green_separator_line).as_str();
-
-				// Display Ore & sol price
 				_current_ore_price=self.load_ore_price();
 				_current_sol_price=self.load_sol_price();
-				if _current_ore_price!=0.00 || _current_sol_price !=0.00 {
-					log_stats+=format!("|       Current ORE Price: {:>17.2} USD\t\tCurrent SOL Price: ${:>.2} USD\n",
-						_current_ore_price,
-						_current_sol_price,
-					).as_str();
-				} else {
-					log_stats+=format!("| No prices are available for ORE & SOL so setting them to $0.00. Consider setting up a coingecko api key as described in the README\n").as_str();
-				}
-
-				// Display Max Reward
-				if max_reward_text != "" {
-					log_stats+=format!("{}\n", max_reward_text).as_str();
-				}
-				
-				// Display Average Reward
-				log_stats+=format!("|          Average reward: {} ORE  (${:>.4}) over {} passes\t\t\t{}\n",
-					format!("{:>17.11}", (session_ore_mined / (pass-1) as f64)).green(),
-					(session_ore_mined / (pass-1) as f64) * _current_ore_price,
-					(pass-1).to_string().yellow(),
-					format!("[~{:.4}% of supply]", (session_ore_mined / (pass-1) as f64) * 100.0).dimmed(),
-				).as_str();
-
-				
-				log_stats+=format!("|         Session Summary: {:>17}               {:>11}        Cost (Cloud)\n", "Profit", "Cost").as_str();
-
-				let session_duration_hours = (pass - 1) as f64 / 60.0;
-				let cloud_usage_cost = rig_cost_per_hour * session_duration_hours;
-				
-				log_stats+=format!("|                  Tokens: {} ORE           {} SOL    {:.3} hours @ ${:.2}/hour\n",
-					format!("{:>17.11}", session_ore_mined).green(),
-					format!("{:>11.6}", session_sol_used).bright_cyan(),
-					session_duration_hours,
-					rig_cost_per_hour,
-				).as_str();
-				
-				log_stats+=format!("|              In dollars: {:>17.02} USD           {:>11.2} USD    {:.2} USD\n",
-					(session_ore_mined * _current_ore_price),
-					(session_sol_used * _current_sol_price),
-					cloud_usage_cost,
-				).as_str();
-				
-				log_stats+=format!("|          Profitablility: {} USD\n",
-					format!("{:>17.2}", (session_ore_mined * _current_ore_price) - (session_sol_used * _current_sol_price) - cloud_usage_cost).bright_green(),
-				).as_str();
-
-				// Calculate average hashes per second
-				let total_mining_time = mining_start_time.elapsed().as_secs() as f64;
-				let hash_benchmark_hps = if total_mining_time > 0.0 {
-					session_hashes as f64 / total_mining_time
-				} else {
-					0.0
-				};
-
-				log_stats += &format!(
-					"| Total Hashes in session: {:.1}M\t\tAverage Hashes per pass: {:.0}\t\tThreads: {}\t{}\n",
-					(session_hashes as f64) / 1048576.0,  // Calc Mega Hashes
-					session_hashes as f64 / (pass-1) as f64,
-					args.threads,
-					format!("[{:>6.2} H/s]", hash_benchmark_hps).dimmed()
-				);
-				log_stats+=format!("|\n| Difficulties solved during {} passes:\n", pass-1).as_str();
-
-				let mut max_count: u32 = 0;
-				let mut most_popular_difficulty: u32 = 0;
-				log_stats+=format!("|------------").as_str();	// Difficulty title row
-				for (difficulty, count) in &difficulties_solved {
-					if (*count as u32) >= max_count {
-						max_count=*count as u32;
-						most_popular_difficulty=*difficulty;
-					}
-					log_stats+=format!("|----").as_str();
-				}
-				log_stats+=format!("|\n").as_str();
-
-				log_stats+=format!("| Difficulty ").as_str();	// solved difficulty levels
-				for (difficulty, _count) in &difficulties_solved {
-					if *difficulty == most_popular_difficulty {
-						log_stats+=format!("|{:>4}", difficulty.to_string().bold().yellow()).as_str();
-					} else {
-						log_stats+=format!("|{:>4}", difficulty).as_str();
-					}
-				}
-				log_stats+=format!("|\n").as_str();
-
-				log_stats+=format!("| Solves     ").as_str();	// solved difficulty counts
-				let mut total_solves=0;
-				for (_difficulty, count) in &difficulties_solved {
-					if (*count as u32) == max_count {
-						log_stats+=format!("|{:>4}", (*count as u32).to_string().bold().yellow()).as_str();
-					} else {
-						log_stats+=format!("|{:>4}", count).as_str();
-					}
-					total_solves+=*count as u32;
-				}
-				log_stats+=format!("|\n").as_str();
-
-				log_stats+=format!("| Percentage ").as_str();	// solved percentage row
-				let mut cumulative=0.0;
-				for (_difficulty, count) in &difficulties_solved {
-					let percent=(*count as f64)*100.0/(total_solves as f64);
-					cumulative += percent;
-					let display_val=f64::trunc(percent) as u32;
-					if (*count as u32) == max_count {
-						log_stats+=format!("|{:>3}{}", display_val.to_string().bold().yellow(), "%".dimmed()).as_str();
-					} else if cumulative<20.0 || (cumulative-percent)>85.0 {
-						log_stats+=format!("|{:>3}{}", display_val.to_string().dimmed(), "%".dimmed()).as_str();
-					} else {
-						log_stats+=format!("|{:>3}{}", display_val, "%".dimmed()).as_str();
-					}
-				}
-				log_stats+=format!("|\n").as_str();
-
-				log_stats+=format!("| Cumulative ").as_str();	// solved cumulative percentage Row
-				cumulative=0.0;
-				for (_difficulty, count) in &difficulties_solved {
-					let percent=(*count as f64)*100.0/(total_solves as f64);
-					cumulative += percent;
-					let display_val=f64::trunc(cumulative) as u32;
-					if (*count as u32) == max_count {
-						log_stats+=format!("|{:>3}{}", display_val.to_string().bold().yellow(), "%".dimmed()).as_str();
-					} else if cumulative<20.0 || (cumulative-percent)>85.0 {
-						log_stats+=format!("|{:>3}{}", display_val.to_string().dimmed(), "%".dimmed()).as_str();
-					} else {
-						log_stats+=format!("|{:>3}{}", display_val, "%".dimmed()).as_str();
-					}
-				}
-
-
-				if pass < 10 {
-					log_stats += format!("\n\n| Base Rate Change in Grains for pass {}:\n", pass-1).as_str();
-				} else {
-					log_stats += "\n\n| Base Rate Change in Grains for last 10 passes:\n";
-				}
-
-				if !base_rate_history.is_empty() {
-					log_stats += &format_base_rate_change_table(&base_rate_history);
-				}
-
-				log_stats+=format!("|\n").as_str();
-				log_stats+=format!("{}\n", green_separator_line).as_str();
+				let prices = Prices { ore: _current_ore_price, sol: _current_sol_price };
+				let profitability = session.profitability(&prices, &running_costs);
+				let report = session_report(&SessionReportInputs {
+					miner_name: &miner_name,
+					wallet_name: &wallet_name,
+					session: &session,
+					prices: &prices,
+					profitability: &profitability,
+					running_costs: &running_costs,
+					threads: last_threads,
+				});
 
 				// Write stat to log file every pass overwriting previous version
-				if stats_logfile != "" {
-					let what_to_log=format!("{}{}{}{}{}", log_stats, log_start_pass, log_hash, log_tx, log_end_pass);
+				if !stats_logfile.is_empty() {
+					let what_to_log=format!("{}{}", report.render(ReportFormat::Text), strip_ansi(&format!("{}{}{}{}", log_start_pass, log_hash, log_tx, log_end_pass)));
 					let _result = write(stats_logfile.clone(), what_to_log);
 				}
 
@@ -591,25 +426,60 @@ impl Miner {
                 let log_info = LogInfo {
 					miner_name: miner_name.clone(),
 					computer_name: computer_name.clone(),
-                    pass: pass - 1,
-                    passes_without_rewards,
+                    pass: session.passes,
+                    passes_without_rewards: session.passes_without_rewards,
                     start_time: Local::now().to_string(),
-                    duration: format_duration(Duration::from_secs(mining_start_time.elapsed().as_secs())).to_string(),
+                    duration: format_duration(Duration::from_secs(session.mining_secs)).to_string(),
+                    elapsed: format_duration(Duration::from_secs((session.elapsed_hours()*3600.0) as u64)).to_string(),
+                    elapsed_hours: session.elapsed_hours(),
+                    active_mining_hours: session.active_hours(),
+                    ore_per_hour: session.ore_per_hour(),
+                    sol_per_hour: session.sol_per_hour(),
+                    hash_rate: session.hash_rate(),
                     cpu_temp: cpu_temp.to_string(),
+                    thermal_throttled: thermal.throttled(),
+                    thermal_threads: last_threads,
+                    thermal_throttle_count: thermal.throttle_count,
+                    thermal_events: thermal.events.clone(),
+                    report: webhook_report_format.map(|format| report.render(format)),
                     load_avg_1min,
                     load_avg_5min,
                     load_avg_15min,
                     current_staked_balance,
                     current_sol_balance,
                     base_reward_rate: amount_u64_to_f64(config.base_reward_rate),
+                    last_pass_signature: outcome.signature.map(|sig| sig.to_string()).unwrap_or_default(),
+                    last_pass_reward_source: outcome.reward_source.to_string(),
                     last_pass_ore_mined,
+                    last_pass_late_rewards: amount_u64_to_f64(outcome.late_rewards),
+                    last_pass_ore_staked: amount_u64_to_f64(outcome.staked),
+                    last_pass_ore_claimed: amount_u64_to_f64(outcome.claimed),
                     last_pass_sol_used,
-                    session_ore_mined,
-                    session_sol_used,
-                    max_reward,
-                    session_hashes,
-                    difficulties_solved: difficulties_solved.clone(),
-                    base_rate_history: base_rate_history.clone(),
+                    last_pass_base_fee: lamports_to_sol(outcome.fees.base),
+                    last_pass_priority_fee: lamports_to_sol(outcome.fees.priority),
+                    last_pass_failed_fee: lamports_to_sol(outcome.fees.failed),
+                    last_pass_failed_transactions: outcome.fees.failed_transactions,
+                    session_ore_mined: session.session_ore_mined,
+                    session_ore_staked: session.session_ore_staked,
+                    session_ore_claimed: session.session_ore_claimed,
+                    session_sol_used: session.session_sol_used,
+                    session_base_fees: lamports_to_sol(session.session_fees.base),
+                    session_priority_fees: lamports_to_sol(session.session_fees.priority),
+                    session_failed_fees: lamports_to_sol(session.session_fees.failed),
+                    session_failed_transactions: session.session_fees.failed_transactions,
+                    session_resets: session.resets,
+                    session_reset_fees: lamports_to_sol(session.reset_fees),
+                    last_pass_energy_kwh: outcome.energy_kwh,
+                    last_pass_energy_cost: outcome.energy_cost,
+                    session_energy_kwh: session.energy_kwh,
+                    session_energy_cost: session.energy_cost,
+                    session_cloud_cost: profitability.cloud_cost,
+                    session_profit: profitability.net,
+                    max_reward: session.max_reward,
+                    session_hashes: session.session_hashes,
+                    difficulties_solved: session.difficulties_solved.clone(),
+                    base_rate_history: session.base_rate_history.clone(),
+                    claims: session.claims.clone(),
 				};	
 
 				// Serialize log info to JSON
@@ -632,40 +502,134 @@ impl Miner {
 				}
 
 				// Display stats on screen every X passes
-				if (pass-1) % 5 == 0 {
-					print!("\n{}", log_stats);
+				if session.stats_pass() {
+					print!("\n{}", report.render(ReportFormat::Terminal));
 				} else {
 					// Add a separator no stats are to be shown
 					println!("\n{}\n", green_separator_line);
 				}
+
+				// Checkpoint the session now the previous pass has been summarised
+				session.save(&session_file);
+				if let Some(combined) = &combined {
+					combined.update(&profile, &session);
+				}
+				stats_report=Some(report);
+			}
+
+			// Stop once the last pass has been summarised if a shutdown has been requested or a run limit reached
+			let stop_reason = if shutdown.requested() {
+				Some(StopReason::Shutdown)
+			} else {
+				run_limits.reached(pass-first_pass, &session)
+			};
+			if let Some(stop_reason) = stop_reason {
+				session.record_elapsed(last_checkpoint.elapsed().as_secs_f64());
+				session.save(&session_file);
+				let log_final=format!("| {} at {} after {} passes: {}\n{}\n",
+					"Mining stopped".bold().green(),
+					Local::now().format("%H:%M:%S on %Y-%m-%d"),
+					session.passes,
+					stop_reason,
+					green_separator_line,
+				);
+				// The stats are only shown every 5 passes so make sure the final stats are shown
+				if let Some(report) = stats_report.as_ref().filter(|_| !session.stats_pass()) {
+					print!("\n{}", report.render(ReportFormat::Terminal));
+				}
+				print!("{}", log_final);
+				if !stats_logfile.is_empty() {
+					let what_to_log=format!("{}{}",
+						stats_report.as_ref().map(|report| report.render(ReportFormat::Text)).unwrap_or_default(),
+						strip_ansi(&format!("{}{}{}{}\n{}", log_start_pass, log_hash, log_tx, log_end_pass, log_final)),
+					);
+					let _result = write(stats_logfile.clone(), what_to_log);
+				}
+				return stop_reason;
 			}
 
 			// Reset Stats Log
-			log_stats=String::from("");
+			stats_report=None;
+
+			// Pause outside the mining schedule. The wait uses up this pass so start a new one once it is over.
+			let window = timetable.as_ref().map(|timetable| timetable.window_at(Local::now()));
+			if let Some(None) = window {
+				let timetable = timetable.as_ref().unwrap();
+				let pause_start_time = Instant::now();
+				self.wait_for_window(timetable, &shutdown, || run_limits.reached(pass-first_pass, &session).is_some()).await;
+				// The rig idles while paused, at the tariff used outside the mining windows
+				let (energy_kwh, energy_cost)=running_costs.energy(0.0, pause_start_time.elapsed().as_secs_f64(), None);
+				session.record_pause(energy_kwh, energy_cost);
+				session.save(&session_file);
+				continue;
+			}
+			let window = window.flatten();
+			// A window's thread count is for the whole process so each wallet takes its share of it
+			let threads = match window.and_then(|w| w.threads) {
+				Some(window_threads) => (window_threads * profile.threads / args.threads.max(1)).max(1),
+				None => profile.threads,
+			};
+			// Mine with fewer threads, or pause first, while the CPU is too hot
+			let thermal_decision = thermal.decide(if cpu_temp==-99.0 { None } else { Some(cpu_temp) }, threads);
+			let threads = thermal_decision.threads;
+			last_threads = threads;
+
+			// Claim the staked ORE if an automatic claim is due. The proof is fetched again afterwards so that
+			// the claim is not counted a second time when the next pass is settled. A wallet without enough SOL
+			// waits to be funded below and claims on a later pass.
+			let mut proof=proof;
+			let mut log_claim=String::from("");
+			if auto_claim.enabled() && sol_balance.is_some_and(|balance| !self.low_sol_balance(balance)) && auto_claim.due(&proof, &session) {
+				match self.auto_claim(&proof, &auto_claim).await {
+					Ok(claim) => {
+						log_claim+=format!("  Auto claimed: {} ORE to {}   Fee: {:.6} SOL\n",
+							format!("{:>17.11}", amount_u64_to_f64(claim.amount)).bright_cyan(),
+							claim.to,
+							lamports_to_sol(claim.fees.total()),
+						).as_str();
+						session.record_claim(claim);
+						session.save(&session_file);
+						proof=get_proof_with_authority(&self.rpc_client, signer.pubkey()).await;
+						current_staked_balance=amount_u64_to_f64(proof.balance);
+					}
+					// The transactions of a failed claim still cost SOL
+					Err(fees) => {
+						log_claim+=format!("  Auto claim failed   Fee: {:.6} SOL\n", lamports_to_sol(fees.total())).as_str();
+						session.record_fees(&fees);
+						session.save(&session_file);
+					}
+				}
+			}
 
-			// Store this pass's sol/staked balances for use in the next pass
-			last_sol_balance=current_sol_balance;
-			last_staked_balance=current_staked_balance;
+			let mut outcome=PassOutcome::new(pass);
 
 			// New pass has started - log pass information
 			log_end_pass=String::from("");
 			log_mined=String::from("");
 			log_start_pass=String::from("");
-			log_start_pass+=format!("Pass {}[{}] started at {}\t\tMined for {}\tCPU: {}{:.2}/{:.2}/{:.2}\n",
+			log_start_pass+=format!("{}Pass {}[{}] started at {}\t\tMined for {}\tCPU: {}{:.2}/{:.2}/{:.2}\n",
+				profile.label,
 				pass,
-				passes_without_rewards,
+				session.passes_without_rewards,
 				Local::now().format("%H:%M:%S on %Y-%m-%d").to_string(),
-				format_duration(Duration::from_secs(mining_start_time.elapsed().as_secs())),
+				format_duration(Duration::from_secs(session.mining_secs)),
 				cpu_temp_txt,
 				load_avg_1min,
 				load_avg_5min,
 				load_avg_15min,
 			).as_str();
+			log_start_pass+=log_claim.as_str();
+			if let Some(event) = &thermal_decision.event {
+				log_start_pass+=format!("  {} {}\n", "Thermal throttle:".bold().red(), event.describe().red()).as_str();
+			}
+			if let Some(window) = window {
+				log_start_pass+=format!("  Mining window: {}   Threads: {}\n", window.description, threads).dimmed().to_string().as_str();
+			}
 
 			// Fetch the current config at the start of each loop
 			let config = get_config(&self.rpc_client).await;
 			let base_rate = amount_u64_to_f64(config.base_reward_rate);
-            update_base_rate_history(&mut base_rate_history, base_rate);
+            session.record_base_rate(base_rate);
 
 			// New pass - log staked & balance details
             log_start_pass+=format!("        Currently Staked: {:>17.11} ORE   Wallet: {:>11.6} SOL    Base Reward Rate: {:.11} ORE\n",
@@ -676,88 +640,147 @@ impl Miner {
 			print!("{}", log_start_pass);
 
 
-			// Pause mining for one minute if no SOL available for transaction fee
-			// This keeps the miner looping and will restart mining when enough SOL is added to miner's wallet
-			if current_sol_balance<MIN_SOL_BALANCE {
-				let progress_bar = Arc::new(spinner::new_progress_bar());
-				for _ in 0..60 {
-					progress_bar.set_message(format!("[{}{}] {}",
-						(60-pass_start_time.elapsed().as_secs()).to_string().dimmed(),
-						("s to go").dimmed(),
-						("Not enough sol in wallet. Please deposit more to continue mining after the timeout.").yellow(),
-					));
-					std::thread::sleep(Duration::from_millis(1000));
-				}
-				let mut log_no_sol=String::from("");
-				log_no_sol+=format!("[{}{}] {}\n",
-					(60-pass_start_time.elapsed().as_secs()).to_string().dimmed(),
-					("s").dimmed(),
-					("Not enough sol in wallet. Please deposit more to continue mining.").yellow(),
-				).as_str();
-				progress_bar.finish_with_message(log_no_sol.clone());
-				log_end_pass+=log_no_sol.as_str();
+			// Wait for the wallet to be funded if there is not enough SOL to pay for the transactions. An unknown
+			// balance does not pause mining, the transactions will fail if the wallet really is empty.
+			if sol_balance.is_none() {
+				println!("{}", "Could not look up the SOL balance, skipping the low balance check for this pass".yellow());
+			}
+			if sol_balance.is_some_and(|balance| self.low_sol_balance(balance)) {
+				let pause_start_time = Instant::now();
+				self.wait_for_funding(current_sol_balance, &log_webhook, &shutdown).await;
+				// The rig idles while it waits
+				let (energy_kwh, energy_cost)=running_costs.energy(0.0, pause_start_time.elapsed().as_secs_f64(), window.and_then(|w| w.tariff));
+				session.record_pause(energy_kwh, energy_cost);
+				session.save(&session_file);
+				// The wait will have used up this pass so start a new one
+				continue;
 			}
 
+			// Let the CPU cool down. The pass is scheduled afterwards so it is shortened to make up for the pause.
+			if thermal_decision.pause_secs>0 {
+				tokio::time::sleep(Duration::from_secs(thermal_decision.pause_secs)).await;
+			}
 
-
-			// Recalculate the desired difficulty level if using dynamic mode
-            if use_dynamic_difficulty {
-                let config = get_config(&self.rpc_client).await;
-                rig_desired_difficulty_level = Self::calculate_optimal_difficulty(
-                    config.base_reward_rate,
-                    config.min_difficulty
-                );
-            }
+			// Work out how long this pass can mine for from the time since the last hash and the liveness penalty
+			let schedule = self.schedule_pass(&proof, args.buffer_time).await;
+			let cutoff_time = schedule.cutoff_time;
+			let log_schedule = format!("  Schedule: {}\n", schedule.explain());
+			print!("{}", log_schedule);
+			log_start_pass+=log_schedule.as_str();
+
+			// Ask the difficulty strategy which difficulty this pass should aim for
+			let decision = difficulty_strategy.decide(&DifficultyInputs {
+				config: &config,
+				session: &session,
+				cutoff_time,
+			});
+			rig_desired_difficulty_level=decision.difficulty;
+			let log_difficulty=format!("  Target difficulty: {} [{} hits] using {} strategy: {}\n",
+				rig_desired_difficulty_level,
+				session.difficulty_hits(rig_desired_difficulty_level),
+				difficulty_strategy.name(),
+				decision.reason,
+			);
+			print!("{}", log_difficulty.dimmed());
+			log_start_pass+=log_difficulty.as_str();
 
 			// The proof of work processing for this individual mining pass
-			if current_sol_balance>=MIN_SOL_BALANCE {
-				log_hash=String::from("");
-				// Run drillx
-        		let (solution, best_difficulty, num_hashes, log) = Self::find_hash_par(proof, cutoff_time, args.threads, rig_desired_difficulty_level).await;
-				log_hash+="  ";
-				log_hash+=log.as_str();
-				log_hash+="\n";
-				
-				// Submit most difficult hash
-				let config = get_config(&self.rpc_client).await;
-				let mut compute_budget = 500_000;
-				let mut ixs = vec![ore_api::instruction::auth(proof_pubkey(signer.pubkey()))];
-				if self.should_reset(config).await && rand::thread_rng().gen_range(0..100).eq(&0) {
-					compute_budget += 100_000;
-					ixs.push(ore_api::instruction::reset(signer.pubkey()));
+			log_hash=String::from("");
+			// Past the cutoff, keep hashing only while it is expected to earn more than the liveness penalty costs
+			let overtime_policy = OvertimePolicy {
+				config,
+				liveness_secs: schedule.liveness_secs,
+				max_overtime: args.max_overtime,
+			};
+			// Run drillx
+			let hashing_start_time = Instant::now();
+			let (solution, best_difficulty, num_hashes, log) = self.find_hash_par(&hash_pool, HashJob::new(proof.challenge, threads), cutoff_time, rig_desired_difficulty_level, overtime_policy, &shutdown).await;
+			outcome.busy_secs=hashing_start_time.elapsed().as_secs_f64();
+			log_hash+="  ";
+			log_hash+=log.as_str();
+			log_hash+="\n";
+			
+			// Submit most difficult hash
+			let config = get_config(&self.rpc_client).await;
+			let mut compute_budget = 500_000;
+			let mut ixs = vec![ore_api::instruction::auth(proof_pubkey(signer.pubkey()))];
+			let reset = match args.reset_policy {
+				ResetPolicy::Never => false,
+				ResetPolicy::Always => self.should_reset(config).await,
+				ResetPolicy::Probabilistic => self.should_reset(config).await
+					&& rand::thread_rng().gen_bool((args.reset_rate / 100.0).clamp(0.0, 1.0)),
+			};
+			if reset {
+				compute_budget += CU_LIMIT_MINE_RESET;
+				ixs.push(ore_api::instruction::reset(signer.pubkey()));
+				log_hash+=format!("  Including an epoch reset (+{} CU)\n", CU_LIMIT_MINE_RESET).as_str();
+			}
+			outcome.reset=reset;
+			let bus = self.get_best_bus(&mut bus_selector, reward_at_difficulty(&config, best_difficulty)).await;
+			log_hash+=format!("  Bus: {}\n", bus.reason.dimmed()).as_str();
+			ixs.push(ore_api::instruction::mine(
+				signer.pubkey(),
+				signer.pubkey(),
+				bus.address,
+				solution,
+			));
+			// std::thread::sleep(Duration::from_millis(60000)); // debug submitting transactions too late
+			let (mut result, mut signatures) = self.send_and_confirm_tracked(&ixs, ComputeBudget::Fixed(compute_budget), false, true).await;
+
+			// Resubmit the same solution if the transaction failed but the challenge has not moved on.
+			// A new challenge or last hash time means one of the failed attempts actually landed.
+			let mut resubmissions=0;
+			while result.is_err() && resubmissions<args.resubmit_attempts {
+				let current_proof = get_proof_with_authority(&self.rpc_client, signer.pubkey()).await;
+				if current_proof.challenge!=proof.challenge || current_proof.last_hash_at!=proof.last_hash_at {
+					break;
 				}
-				ixs.push(ore_api::instruction::mine(
-                signer.pubkey(),
-					signer.pubkey(),
-					self.find_bus().await,
-					solution,
-				));
-				// std::thread::sleep(Duration::from_millis(60000)); // debug submitting transactions too late
-				match self.send_and_confirm(&ixs, ComputeBudget::Fixed(compute_budget), false, true)
-					.await {
-						Ok(_sig) => {
-							// Log the difficulty solved to hashMap to record progress
-							*difficulties_solved.entry(best_difficulty).or_insert(0) += 1;
-							last_pass_difficulty=best_difficulty;
-						},
-						Err(err) => {
-							log_end_pass+=format!("        {} {}\n", "Transaction failed:".yellow(), err.to_string().yellow()).as_str();
-						},
-					};
-
-				// Duplicate the difficulty log line to stats
-				session_hashes+=num_hashes;
+				resubmissions+=1;
+				let priority_fee = self.resubmit_priority_fee(args.resubmit_fee_increase, resubmissions);
+				let log_resubmit=format!("  Resubmitting the same solution ({} of {}) with a priority fee of {}\n",
+					resubmissions,
+					args.resubmit_attempts,
+					priority_fee,
+				).yellow().to_string();
+				print!("{}", log_resubmit);
+				log_hash+=log_resubmit.as_str();
+				let (retry_result, retry_signatures) = self.resubmit_tracked(&ixs, ComputeBudget::Fixed(compute_budget), priority_fee).await;
+				result=retry_result;
+				signatures.extend(retry_signatures);
 			}
 
-			let optimal_diff_hits = difficulties_solved.iter()
-				.filter(|(&diff, _)| diff >= rig_desired_difficulty_level)
-				.map(|(_, &count)| count)
-				.sum::<usize>();
+			match result {
+				Ok(sig) => {
+					// Log the difficulty solved to record progress
+					outcome.difficulty=Some(best_difficulty);
+					outcome.signature=Some(sig);
+				},
+				Err(err) => {
+					log_end_pass+=format!("        {} {}\n", "Transaction failed:".yellow(), err.to_string().yellow()).as_str();
+				},
+			};
+
+			// Read the fees paid by every transaction that landed and the reward for this pass from the mine transaction
+			for sig in signatures.iter() {
+				if let Some(meta) = get_landed_transaction_meta(&self.rpc_client, sig).await {
+					outcome.fees.add(&meta);
+					// The mine transaction landed even though submitting it reported an error
+					if outcome.signature.is_none() && meta.err.is_none() {
+						outcome.difficulty=Some(best_difficulty);
+						outcome.signature=Some(*sig);
+						log_end_pass+=format!("        {} {}\n", "Transaction landed despite the error:".green(), sig.to_string().dimmed()).as_str();
+					}
+					if outcome.signature==Some(*sig) {
+						outcome.mine_event=parse_mine_event(&meta);
+					}
+				}
+			}
 
-			log_start_pass += &format!("  Current optimal difficulty: {} [{}]\n", 
-				rig_desired_difficulty_level, 
-				optimal_diff_hits
-			).to_string().dimmed().to_string();
+			// The priority fee is charged on the compute units requested so part of it paid for the reset
+			if outcome.reset {
+				outcome.reset_fee=outcome.fees.priority * CU_LIMIT_MINE_RESET as u64 / compute_budget as u64;
+			}
+			outcome.hashes=num_hashes;
 
 			// Log how long this pass took to complete
 			log_end_pass+=format!("  [{}{}] Completed",
@@ -766,185 +789,129 @@ impl Miner {
 			).as_str();
 			print!("{}", log_end_pass);
 
+			outcome.duration_secs=pass_start_time.elapsed().as_secs();
+			// The rig is busy while hashing and idle for the rest of the pass
+			(outcome.energy_kwh, outcome.energy_cost)=running_costs.energy(
+				outcome.busy_secs,
+				(pass_start_time.elapsed().as_secs_f64()-outcome.busy_secs).max(0.0),
+				window.and_then(|w| w.tariff),
+			);
+			// Checkpoint the pass with this pass's proof to settle its rewards at the start of the next pass
+			session.pending_pass=Some(PendingPass { outcome, proof_before: proof });
+			session.save(&session_file);
 			pass+=1;
         }
     }
 
-	// This is the main hashing functio for the ORE mining loop
-    async fn find_hash_par(proof: Proof, cutoff_time: u64, threads: u64, rig_desired_difficulty_level: u32) -> (Solution, u32, u64, String) {
-        // Dispatch job to each thread
-		let timer = Instant::now();
-		let progress_bar = Arc::new(spinner::new_progress_bar());
-		let global_max_difficulty = Arc::new(Mutex::new(u32::MIN));
-		let global_max_difficulty_took = Arc::new(Mutex::new(u64::MIN));
-		let global_hashes = Arc::new(Mutex::new(u64::MIN));
-		let stop_all_threads = Arc::new(Mutex::new(false));
+	// This is the main hashing function for the ORE mining loop. It runs the session's hashing threads until the cutoff
+	// has passed and either the desired difficulty is reached or more hashing is no longer worth the liveness penalty.
+    async fn find_hash_par(&self, hash_pool: &Arc<HashPool>, job: HashJob, cutoff_time: u64, rig_desired_difficulty_level: u32, overtime_policy: OvertimePolicy, shutdown: &Shutdown) -> (Solution, u32, u64, String) {
+		let progress_bar = self.new_progress_bar();
+		let submit_reason = Arc::new(Mutex::new(String::new()));
 		progress_bar.set_message(format!("[{}s to go] Mining...", cutoff_time));
-		let handles: Vec<_> = (0..threads)
-            .map(|thread_number| {
-				std::thread::spawn({
-                    let proof = proof.clone();
-                    let progress_bar = progress_bar.clone();
-                    let mut memory = equix::SolverMemory::new();
-					let thread_max_difficulty = Arc::clone(&global_max_difficulty);
-					let thread_max_difficulty_took = Arc::clone(&global_max_difficulty_took);
-					let thread_hashes = Arc::clone(&global_hashes);
-					let thread_stop_all_threads = Arc::clone(&stop_all_threads);
-					move || {
-                        let mut nonce = u64::MAX.saturating_div(threads).saturating_mul(thread_number);
-                        let mut best_nonce = nonce;
-                        let mut best_difficulty = 0;
-                        let mut best_hash = Hash::default();
-						let mut last_elapsed:u64 = 0;
-						let mut hashes=0;
-
-                        loop {
-							hashes+=1;
-
-							// Create hash
-                            if let Ok(hx) = drillx::hash_with_memory(
-                                &mut memory,
-                                &proof.challenge,
-                                &nonce.to_le_bytes(),
-                            ) {
-                                let difficulty = hx.difficulty();
-                                if difficulty.gt(&best_difficulty) {
-                                    best_nonce = nonce;
-                                    best_difficulty = difficulty;
-                                    best_hash = hx;
-									{	// Update the global max difficulty counter
-										let mut global_max_difficulty=thread_max_difficulty.lock().unwrap();
-										if difficulty>*global_max_difficulty {
-											*global_max_difficulty = difficulty+0;
-											let mut global_max_difficult_took=thread_max_difficulty_took.lock().unwrap();
-											*global_max_difficult_took = timer.elapsed().as_secs();
-										}
-									}
-                                }
-                            }
-
-                            // Thread processing every 100 hashes (saves CPU & triggers every second or so)
-                            if nonce % 100 == 0 {
-								let elapsed_secs=timer.elapsed().as_secs();
-								let global_max_difficulty=thread_max_difficulty.lock().unwrap();
-								let mut global_stop_all_threads=thread_stop_all_threads.lock().unwrap();
-								let over_time=elapsed_secs.ge(&cutoff_time);
-
-								// Check if this thread has been asked to stop
-								if *global_stop_all_threads {
-									// this thread has been asked to terminate by another thread
-									break;
-								}
-
-								// Check if we have mined for the appropriate length of time
-								if over_time {
-									// Ask all other threads to stop if we have attained a desired difficulty level
-									if global_max_difficulty.ge(&rig_desired_difficulty_level) {
-										// println!("{} Reached desired difficulty of {}", thread_number, rig_desired_difficulty_level);
-										*global_stop_all_threads = true;
-										break;
-									}
-									// Terminate this thread if we have attained a desired difficulty level
-									// if best_difficulty.gt(config.min_difficulty) {
-									// // if best_difficulty.gt(&ore::MIN_DIFFICULTY) && global_max_difficulty.ge(&rig_desired_difficulty_level) {
-									// 	// Mine until min difficulty has been met
-									// 	break;
-									// }
-									break;
-								}
-
-								// Only log for first thread - other threads are silent
-								if thread_number == 0 {
-									if elapsed_secs != last_elapsed {
-										last_elapsed=elapsed_secs;
-
-										let countdown_text;
-										let mut extended_hashing_txt="";
-										if elapsed_secs<cutoff_time {
-											countdown_text=format!("{}{}",
-												cutoff_time.saturating_sub(elapsed_secs).to_string().dimmed(),
-												"s to go".dimmed(),
-											);
-										} else {
-											countdown_text=format!("{}{}",
-												(elapsed_secs-cutoff_time).to_string().dimmed(),
-												"s over".dimmed(),
-											);
-											extended_hashing_txt="[Extended hashing period]";
-										}
-
-										let mut attained_desired_difficulty="";
-										if global_max_difficulty.ge(&rig_desired_difficulty_level) {
-											attained_desired_difficulty="*";
-										}
-
-										let global_max_difficult_took=thread_max_difficulty_took.lock().unwrap();
-										progress_bar.set_message(format!(
-											"[{}] Mining... {} {}{} after {} secs\tApprox Hashes: {} {}",
-											countdown_text,
-											"Difficulty so far:".dimmed(),
-											global_max_difficulty,
-											attained_desired_difficulty,
-											global_max_difficult_took,
-											hashes*threads,
-											extended_hashing_txt,
-										));
-									}
-								}
-                            }
-
-                            // Increment nonce
-                            nonce += 1;
-                        }
-
-						{
-							let mut global_hashes=thread_hashes.lock().unwrap();
-							*global_hashes += hashes;
-						}
-
-                        // Return the best nonce
-                        (best_nonce, best_difficulty, best_hash)
-                    }
-                })
-            })
-            .collect();
-
-        // Join handles and return best nonce
-        let mut best_nonce = 0;
-        let mut best_difficulty = 0;
-        let mut best_hash = Hash::default();
-        for h in handles {
-            if let Ok((nonce, difficulty, hash)) = h.join() {
-                if difficulty > best_difficulty {
-                    best_difficulty = difficulty;
-                    best_nonce = nonce;
-                    best_hash = hash;
-                }
-            }
-        }
 
-        // Update log
-		let hashes=global_hashes.lock().unwrap();
+		let stop = {
+			let submit_reason = submit_reason.clone();
+			let shutdown = shutdown.clone();
+			let last_overtime_check = AtomicU64::new(0);
+			move |progress: &HashProgress| {
+				let elapsed_secs = progress.elapsed.as_secs();
+				if elapsed_secs < cutoff_time {
+					return false;
+				}
+				// Stop as soon as we have attained the desired difficulty level
+				if progress.best_difficulty.ge(&rig_desired_difficulty_level) {
+					return true;
+				}
+				// Decide once a second whether more hashing is worth the liveness penalty
+				if last_overtime_check.swap(elapsed_secs, Ordering::Relaxed) == elapsed_secs {
+					return false;
+				}
+				let decision = if shutdown.requested() {
+					OvertimeDecision::Submit("shutdown requested".to_string())
+				} else {
+					overtime_policy.decide(progress.best_difficulty, progress.hash_rate(), elapsed_secs, elapsed_secs - cutoff_time)
+				};
+				match decision {
+					OvertimeDecision::Submit(reason) => {
+						*submit_reason.lock().unwrap() = reason;
+						true
+					}
+					OvertimeDecision::KeepHashing => false,
+				}
+			}
+		};
+		let show_progress = {
+			let progress_bar = progress_bar.clone();
+			move |progress: &HashProgress| {
+				let elapsed_secs = progress.elapsed.as_secs();
+				let countdown_text;
+				let mut extended_hashing_txt="";
+				if elapsed_secs<cutoff_time {
+					countdown_text=format!("{}{}",
+						cutoff_time.saturating_sub(elapsed_secs).to_string().dimmed(),
+						"s to go".dimmed(),
+					);
+				} else {
+					countdown_text=format!("{}{}",
+						(elapsed_secs-cutoff_time).to_string().dimmed(),
+						"s over".dimmed(),
+					);
+					extended_hashing_txt="[Extended hashing period]";
+				}
+
+				let mut attained_desired_difficulty="";
+				if progress.best_difficulty.ge(&rig_desired_difficulty_level) {
+					attained_desired_difficulty="*";
+				}
+
+				progress_bar.set_message(format!(
+					"[{}] Mining... {} {}{} after {} secs\tApprox Hashes: {} {}",
+					countdown_text,
+					"Difficulty so far:".dimmed(),
+					progress.best_difficulty,
+					attained_desired_difficulty,
+					progress.best_difficulty_secs,
+					progress.hashes,
+					extended_hashing_txt,
+				));
+			}
+		};
+
+		// The hashing threads block so they are run off the async runtime, which lets other wallets carry on
+		// submitting while this one is hashing.
+		let hash_pool = hash_pool.clone();
+		let result = tokio::task::spawn_blocking(move || {
+			hash_pool.find_hash_par(&job, stop, show_progress)
+		}).await.unwrap_or_default();
+
+		// Update log
 		let mut attained_desired_difficulty="";
-		if best_difficulty.ge(&rig_desired_difficulty_level) {
+		if result.difficulty.ge(&rig_desired_difficulty_level) {
 			attained_desired_difficulty="*";
 		}
 
 		let mut log_hash=String::from("");
 		log_hash+=format!(
-            "[{}{}] Difficulty: {}{} after {} secs   Hashes: {}   Hash: {}",
-			timer.elapsed().as_secs().to_string().dimmed(),
+            "[{}{}] Difficulty: {}{} after {} secs   Hashes: {} ({:.0} H/s, {:.0} checks/s)   Hash: {}",
+			result.elapsed.as_secs().to_string().dimmed(),
 			"s".dimmed(),
-            best_difficulty.to_string().bold().yellow(),
+            result.difficulty.to_string().bold().yellow(),
 			attained_desired_difficulty,
-			global_max_difficulty_took.lock().unwrap().to_string().bold().yellow(),
-			*hashes,
-			// 100.0* (*hashes as f64)/(u64::MAX as f64) *1000000000.0,
-            bs58::encode(best_hash.h).into_string().dimmed(),
+			result.best_difficulty_secs.to_string().bold().yellow(),
+			result.hashes,
+			result.hash_rate(),
+			result.check_rate(),
+            bs58::encode(result.hash.h).into_string().dimmed(),
 		).as_str();
+		let submit_reason=submit_reason.lock().unwrap();
+		let overtime_secs=result.elapsed.as_secs().saturating_sub(cutoff_time);
+		if overtime_secs>0 && !submit_reason.is_empty() {
+			log_hash+=format!("\n  Hashed for {}s past the cutoff: {}", overtime_secs, submit_reason).dimmed().to_string().as_str();
+		}
 		progress_bar.finish_with_message(log_hash.clone());
 
-        (Solution::new(best_hash.d, best_nonce.to_le_bytes()), best_difficulty, *hashes, log_hash)
+        (result.solution(), result.difficulty, result.hashes, log_hash)
     }
 
 	// Ensure that the requested number of threads is not above the number of CPU cores
@@ -970,66 +937,91 @@ impl Miner {
             .le(&clock.unix_timestamp)
     }
 
-	// Calculate how long to mine for
-	// Based upon (last_hash_at time) + (1 minute) - (desired buffer_time) - (clock time)
-    async fn get_cutoff(&self, proof: Proof, buffer_time: u64, clock: &Clock) -> u64 {
-        // clock is passed in to prevent calling get_clock() twice in quick succession from RPC
-		// let clock = get_clock(&self.rpc_client).await;
-        let mut retval=proof.last_hash_at
-            .saturating_add(60)
-            .saturating_sub(buffer_time as i64)
-            .saturating_sub(clock.unix_timestamp.clone())
-            .max(0) as u64;
-		if retval==0 {
-			retval=(60 as i64).saturating_sub(buffer_time as i64).max(0) as u64;
+	// Look up the SOL in the wallet, retrying failed lookups. None if the balance could not be looked up.
+	async fn get_sol_balance(&self) -> Option<f64> {
+		for attempt in 0..SOL_BALANCE_RETRIES {
+			if attempt>0 {
+				tokio::time::sleep(Duration::from_millis(SOL_BALANCE_RETRY_MILLIS)).await;
+			}
+			if let Some(sol_balance) = self.get_sol_balance_tx().await {
+				return Some(sol_balance)
+			}
 		}
-		return retval;
-    }
+		None
+	}
+
+	// Whether a wallet has too little SOL to pay for transactions
+	pub fn low_sol_balance(&self, sol_balance: f64) -> bool {
+		sol_balance<self.min_sol_balance
+	}
 
-	async fn get_sol_balance(&self, panic: bool) -> f64 {
-		let mut current_sol_balance=self.get_sol_balance_tx(panic).await;
-		if current_sol_balance==0.0 {
-			for _ in 0..50 {
-				std::thread::sleep(Duration::from_millis(50));
-				current_sol_balance=self.get_sol_balance_tx(panic).await;
-				if current_sol_balance>=0.0 {
-					return current_sol_balance
+	// Wait until the wallet has enough SOL to pay for transactions, polling its balance so mining resumes as
+	// soon as it has been funded. The webhook is told when the wallet runs low and when it has been funded.
+	async fn wait_for_funding(&self, sol_balance: f64, log_webhook: &str, shutdown: &Shutdown) {
+		println!("{}", format!("Not enough SOL in wallet: {:.6} SOL. Please deposit at least {} SOL to continue mining.",
+			sol_balance,
+			self.min_sol_balance,
+		).yellow());
+		self.send_sol_balance_alert(log_webhook, "low_sol_balance", sol_balance).await;
+
+		let wait_start_time = Instant::now();
+		let progress_bar = self.new_progress_bar();
+		let mut sol_balance = sol_balance;
+		let mut last_poll = Instant::now();
+		while self.low_sol_balance(sol_balance) {
+			if shutdown.requested() {
+				progress_bar.finish_with_message("Stopped waiting for the wallet to be funded".yellow().to_string());
+				return;
+			}
+			if last_poll.elapsed().as_secs()>=SOL_BALANCE_POLL_SECS {
+				// Keep the last known balance while it cannot be looked up
+				if let Some(balance) = self.get_sol_balance().await {
+					sol_balance=balance;
 				}
+				last_poll=Instant::now();
 			}
+			progress_bar.set_message(format!("[{}] {} {:.6} SOL",
+				format_duration(Duration::from_secs(wait_start_time.elapsed().as_secs())).to_string().dimmed(),
+				"Waiting for the wallet to be funded. Balance:".yellow(),
+				sol_balance,
+			));
+			tokio::time::sleep(Duration::from_secs(1)).await;
 		}
-		return current_sol_balance
+		progress_bar.finish_with_message(format!("{} {:.6} SOL after waiting {}",
+			"Wallet funded:".green(),
+			sol_balance,
+			format_duration(Duration::from_secs(wait_start_time.elapsed().as_secs())),
+		));
+		self.send_sol_balance_alert(log_webhook, "sol_balance_funded", sol_balance).await;
 	}
 
-	// Query the wallet for the amount of SOL present and panic if less than a minimum amount
-	async fn get_sol_balance_tx(&self, panic: bool) -> f64 {
-		const MIN_SOL_BALANCE: f64 = 0.005;
+	async fn send_sol_balance_alert(&self, log_webhook: &str, event: &str, sol_balance: f64) {
+		if log_webhook.is_empty() {
+			return;
+		}
+		let alert = SolBalanceAlert {
+			event: event.to_string(),
+			miner_name: env::var("MINER_NAME").unwrap_or("".to_string()),
+			computer_name: hostname::get()
+				.map(|s| s.to_string_lossy().into_owned())
+				.unwrap_or_else(|_| "Unknown".to_string()),
+			wallet: self.signer().pubkey().to_string(),
+			sol_balance,
+			min_sol_balance: self.min_sol_balance,
+			time: Local::now().to_string(),
+		};
+		let json_alert = to_string_pretty(&alert).unwrap();
+		match Self::send_log_to_webhook(log_webhook.to_string(), json_alert).await {
+			Ok(_) => println!("SOL balance alert sent to webhook: {}", log_webhook),
+			Err(e) => eprintln!("Failed to send SOL balance alert to webhook: {}. Error: {}", log_webhook, e),
+		}
+	}
+
+	// Query the wallet for the amount of SOL present, None if the lookup failed
+	async fn get_sol_balance_tx(&self) -> Option<f64> {
 		let signer = self.signer();
 		let client = self.rpc_client.clone();
-
-		// Return error, if balance is zero
-		if let Ok(lamports_balance) = client.get_balance(&signer.pubkey()).await {
-			let sol_balance:f64 = lamports_to_sol(lamports_balance);
-			if lamports_balance <= sol_to_lamports(MIN_SOL_BALANCE) {
-				if panic {
-					panic!(
-						"{} Insufficient balance: {} SOL\nPlease top up with at least {} SOL",
-						"ERROR".bold().red(),
-						sol_balance,
-						MIN_SOL_BALANCE
-					);
-				}
-			}
-			return sol_balance
-		} else {
-			if panic {
-				panic!(
-					"{} Failed to lookup sol balance",
-					"ERROR".bold().red(),
-				);
-			} else {
-				return 0.0 as f64
-			}
-		}
+		client.get_balance(&signer.pubkey()).await.ok().map(lamports_to_sol)
 	}
 
 	// Read a file to get a f64 value from the first line of the file
@@ -1093,8 +1085,4 @@ impl Miner {
     	}
 	}
 
-    async fn find_bus(&self) -> Pubkey {
-        let address = self.get_best_bus().await;
-        address
-    }
 }
\ No newline at end of file
@@ -0,0 +1,530 @@
+use std::collections::VecDeque;
+use std::env;
+use std::time::Duration;
+
+use chrono::prelude::*;
+use colored::*;
+use humantime::format_duration;
+use solana_program::native_token::lamports_to_sol;
+
+use crate::session::{BaseRateInfo, MiningSession, Prices, Profitability, RunningCosts};
+
+const GRAINS_PER_ORE: u64 = 100_000_000_000; // 100 billion grains per ORE
+
+// The width of the separator lines in the terminal and text reports
+const REPORT_WIDTH: usize = 135;
+
+// How a piece of text is emphasised. Renderers map this to ANSI colours, HTML colours or Markdown.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Plain,
+    Bold,
+    Good,           // ORE amounts and rising values
+    Profit,
+    Highlight,      // The most notable value in a row
+    Info,           // SOL amounts
+    Bad,            // Falling values
+    Dim,
+}
+
+// A piece of text in a report
+#[derive(Clone)]
+pub struct Cell {
+    pub text: String,
+    pub style: Style,
+}
+
+impl Cell {
+    pub fn new(text: impl Into<String>, style: Style) -> Self {
+        Self { text: text.into(), style }
+    }
+
+    pub fn plain(text: impl Into<String>) -> Self {
+        Self::new(text, Style::Plain)
+    }
+}
+
+// A labelled line of a report, e.g. "Average reward: 0.01 ORE over 5 passes"
+pub struct ReportLine {
+    pub label: String,
+    pub cells: Vec<Cell>,
+}
+
+impl ReportLine {
+    // The line as it appears in the terminal and text reports
+    pub fn render_text(&self, colored: bool) -> String {
+        let cells = self.cells.iter().map(|cell| if colored { ansi(cell) } else { cell.text.clone() }).collect::<Vec<_>>().join(" ");
+        if self.label.is_empty() {
+            format!("| {}", cells)
+        } else {
+            format!("| {:>23}: {}", self.label, cells)
+        }
+    }
+}
+
+// A table with a label at the start of each row
+pub struct ReportTable {
+    pub header: Vec<Cell>,
+    pub rows: Vec<(String, Vec<Cell>)>,
+}
+
+pub enum ReportSection {
+    Lines(Vec<ReportLine>),
+    Table(Option<String>, ReportTable),
+}
+
+// A report of the mining stats that can be rendered for the terminal, a log file or a webhook
+pub struct StatsReport {
+    pub title: String,
+    pub sections: Vec<ReportSection>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Terminal,       // Coloured text
+    Text,           // Text without escape codes for log files
+    Markdown,
+    Html,
+}
+
+impl ReportFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "terminal" => Some(Self::Terminal),
+            "text" => Some(Self::Text),
+            "markdown" | "md" => Some(Self::Markdown),
+            "html" => Some(Self::Html),
+            _ => None,
+        }
+    }
+}
+
+// The format of the report sent to the webhook, from STATS_REPORT_FORMAT. Set it to none to leave the report out.
+pub fn webhook_report_format_from_env() -> Option<ReportFormat> {
+    match env::var("STATS_REPORT_FORMAT") {
+        Ok(value) if value.to_lowercase() == "none" => None,
+        Ok(value) if !value.is_empty() => ReportFormat::parse(&value).or_else(|| {
+            eprintln!("Warning: unknown report format {}. Using markdown.", value);
+            Some(ReportFormat::Markdown)
+        }),
+        _ => Some(ReportFormat::Markdown),
+    }
+}
+
+impl StatsReport {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            sections: vec![],
+        }
+    }
+
+    pub fn line(&mut self, label: impl Into<String>, cells: Vec<Cell>) {
+        let line = ReportLine { label: label.into(), cells };
+        match self.sections.last_mut() {
+            Some(ReportSection::Lines(lines)) => lines.push(line),
+            _ => self.sections.push(ReportSection::Lines(vec![line])),
+        }
+    }
+
+    pub fn table(&mut self, heading: Option<String>, table: ReportTable) {
+        self.sections.push(ReportSection::Table(heading, table));
+    }
+
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Terminal => self.render_text(true),
+            ReportFormat::Text => self.render_text(false),
+            ReportFormat::Markdown => self.render_markdown(),
+            ReportFormat::Html => self.render_html(),
+        }
+    }
+
+    fn render_text(&self, colored: bool) -> String {
+        let paint = |cell: &Cell| if colored { ansi(cell) } else { cell.text.clone() };
+        let separator = if colored { "=".repeat(REPORT_WIDTH).dimmed().green().to_string() } else { "=".repeat(REPORT_WIDTH) };
+        let title = if colored { format!("| {}", self.title).green().to_string() } else { format!("| {}", self.title) };
+        let mut text = format!("{}\n{}\n{}\n", separator, title, separator);
+        for section in &self.sections {
+            match section {
+                ReportSection::Lines(lines) => {
+                    for line in lines {
+                        text += &format!("{}\n", line.render_text(colored));
+                    }
+                }
+                ReportSection::Table(heading, table) => {
+                    if let Some(heading) = heading {
+                        text += &format!("|\n| {}\n", heading);
+                    }
+                    let label_width = table.rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0).max(10);
+                    let widths = column_widths(table);
+                    let rule = format!("|-{}-|{}|\n", "-".repeat(label_width), widths.iter().map(|w| "-".repeat(*w)).collect::<Vec<_>>().join("|"));
+                    text += &rule;
+                    if !table.header.is_empty() {
+                        text += &format!("| {:<label_width$} |", "");
+                        for (cell, width) in table.header.iter().zip(&widths) {
+                            text += &format!("{}|", paint(&pad(cell, *width)));
+                        }
+                        text += "\n";
+                        text += &rule;
+                    }
+                    for (label, cells) in &table.rows {
+                        text += &format!("| {:<label_width$} |", label);
+                        for (cell, width) in cells.iter().zip(&widths) {
+                            text += &format!("{}|", paint(&pad(cell, *width)));
+                        }
+                        text += "\n";
+                    }
+                    text += &rule;
+                }
+            }
+        }
+        text += &format!("{}\n", separator);
+        text
+    }
+
+    fn render_markdown(&self) -> String {
+        let mut markdown = format!("### {}\n\n", self.title);
+        for section in &self.sections {
+            match section {
+                ReportSection::Lines(lines) => {
+                    for line in lines {
+                        let cells = line.cells.iter().map(markdown_cell).collect::<Vec<_>>().join(" ");
+                        if line.label.is_empty() {
+                            markdown += &format!("- {}\n", cells);
+                        } else {
+                            markdown += &format!("- **{}:** {}\n", line.label, cells);
+                        }
+                    }
+                    markdown += "\n";
+                }
+                ReportSection::Table(heading, table) => {
+                    if let Some(heading) = heading {
+                        markdown += &format!("**{}**\n\n", heading);
+                    }
+                    let columns = column_widths(table).len();
+                    let header = if table.header.is_empty() { vec![Cell::plain(""); columns] } else { table.header.clone() };
+                    markdown += &format!("| | {} |\n", header.iter().map(markdown_cell).collect::<Vec<_>>().join(" | "));
+                    markdown += &format!("|---|{}\n", "---:|".repeat(columns));
+                    for (label, cells) in &table.rows {
+                        markdown += &format!("| {} | {} |\n", label, cells.iter().map(markdown_cell).collect::<Vec<_>>().join(" | "));
+                    }
+                    markdown += "\n";
+                }
+            }
+        }
+        markdown
+    }
+
+    fn render_html(&self) -> String {
+        let mut html = format!("<h3>{}</h3>\n", escape_html(&self.title));
+        for section in &self.sections {
+            match section {
+                ReportSection::Lines(lines) => {
+                    html += "<ul>\n";
+                    for line in lines {
+                        let cells = line.cells.iter().map(html_cell).collect::<Vec<_>>().join(" ");
+                        if line.label.is_empty() {
+                            html += &format!("<li>{}</li>\n", cells);
+                        } else {
+                            html += &format!("<li><b>{}:</b> {}</li>\n", escape_html(&line.label), cells);
+                        }
+                    }
+                    html += "</ul>\n";
+                }
+                ReportSection::Table(heading, table) => {
+                    if let Some(heading) = heading {
+                        html += &format!("<p><b>{}</b></p>\n", escape_html(heading));
+                    }
+                    html += "<table>\n";
+                    if !table.header.is_empty() {
+                        html += &format!("<tr><th></th>{}</tr>\n",
+                            table.header.iter().map(|c| format!("<th>{}</th>", html_cell(c))).collect::<String>(),
+                        );
+                    }
+                    for (label, cells) in &table.rows {
+                        html += &format!("<tr><th>{}</th>{}</tr>\n",
+                            escape_html(label),
+                            cells.iter().map(|c| format!("<td align=\"right\">{}</td>", html_cell(c))).collect::<String>(),
+                        );
+                    }
+                    html += "</table>\n";
+                }
+            }
+        }
+        html
+    }
+}
+
+fn column_widths(table: &ReportTable) -> Vec<usize> {
+    let columns = table.rows.iter().map(|(_, cells)| cells.len()).chain([table.header.len()]).max().unwrap_or(0);
+    (0..columns)
+        .map(|i| {
+            table.rows.iter().filter_map(|(_, cells)| cells.get(i))
+                .chain(table.header.get(i))
+                .map(|cell| cell.text.chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect()
+}
+
+fn pad(cell: &Cell, width: usize) -> Cell {
+    Cell::new(format!("{:>width$}", cell.text), cell.style)
+}
+
+fn ansi(cell: &Cell) -> String {
+    let text = cell.text.as_str();
+    match cell.style {
+        Style::Plain => text.to_string(),
+        Style::Bold => text.bold().to_string(),
+        Style::Good => text.green().to_string(),
+        Style::Profit => text.bright_green().to_string(),
+        Style::Highlight => text.bold().yellow().to_string(),
+        Style::Info => text.bright_cyan().to_string(),
+        Style::Bad => text.red().to_string(),
+        Style::Dim => text.dimmed().to_string(),
+    }
+}
+
+fn markdown_cell(cell: &Cell) -> String {
+    let text = cell.text.trim();
+    match cell.style {
+        Style::Bold | Style::Highlight | Style::Profit if !text.is_empty() => format!("**{}**", text),
+        Style::Dim if !text.is_empty() => format!("_{}_", text),
+        _ => text.to_string(),
+    }
+}
+
+fn html_cell(cell: &Cell) -> String {
+    let text = escape_html(cell.text.trim());
+    match cell.style {
+        Style::Plain => text,
+        Style::Bold => format!("<b>{}</b>", text),
+        Style::Good => format!("<span style=\"color:green\">{}</span>", text),
+        Style::Profit => format!("<b style=\"color:green\">{}</b>", text),
+        Style::Highlight => format!("<b style=\"color:orange\">{}</b>", text),
+        Style::Info => format!("<span style=\"color:darkcyan\">{}</span>", text),
+        Style::Bad => format!("<span style=\"color:red\">{}</span>", text),
+        Style::Dim => format!("<span style=\"color:gray\">{}</span>", text),
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+// Remove the ANSI escape codes from coloured terminal output so that it can be written to a log file
+pub fn strip_ansi(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            // Skip the control sequence up to and including its final letter
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+// What the session stats report is built from
+pub struct SessionReportInputs<'a> {
+    pub miner_name: &'a str,
+    pub wallet_name: &'a str,
+    pub session: &'a MiningSession,
+    pub prices: &'a Prices,
+    pub profitability: &'a Profitability,
+    pub running_costs: &'a RunningCosts,
+    pub threads: u64,
+}
+
+// The highest reward of the session, in the stats and when a pass has just mined it
+pub fn max_reward_line(session: &MiningSession, ore_price: f64) -> ReportLine {
+    ReportLine {
+        label: "Max session reward".to_string(),
+        cells: vec![
+            Cell::new(format!("{:>17.11}", session.max_reward), Style::Good),
+            Cell::plain(format!("ORE  (${:.2}) at difficulty", session.max_reward * ore_price)),
+            Cell::new(session.max_reward_difficulty.to_string(), Style::Highlight),
+            Cell::plain("during pass"),
+            Cell::new(session.max_reward_pass.to_string(), Style::Highlight),
+            Cell::new(format!("\t[~{:.4}% of supply]", session.max_reward * 100.0), Style::Dim),
+        ],
+    }
+}
+
+// The stats of a mining session, shown every few passes and when mining stops
+pub fn session_report(inputs: &SessionReportInputs) -> StatsReport {
+    let session = inputs.session;
+    let prices = inputs.prices;
+    let profitability = inputs.profitability;
+    let mut report = StatsReport::new(format!("Stats for {} pass {} at {}\t[{}]\tStarted at {}",
+        inputs.miner_name,
+        session.passes,
+        Local::now().format("%H:%M:%S on %Y-%m-%d"),
+        inputs.wallet_name,
+        session.started_at_local().format("%H:%M:%S on %Y-%m-%d"),
+    ));
+
+    // Display Ore & sol price
+    if prices.ore != 0.0 || prices.sol != 0.0 {
+        report.line("Current ORE Price", vec![
+            Cell::plain(format!("{:>17.2} USD\t\tCurrent SOL Price: ${:>.2} USD", prices.ore, prices.sol)),
+        ]);
+    } else {
+        report.line("", vec![
+            Cell::plain("No prices are available for ORE & SOL so setting them to $0.00. Consider setting up a coingecko api key as described in the README"),
+        ]);
+    }
+
+    // Display Max Reward
+    if session.max_reward > 0.0 {
+        let line = max_reward_line(session, prices.ore);
+        report.line(line.label, line.cells);
+    }
+
+    // Display Average Reward
+    report.line("Average reward", vec![
+        Cell::new(format!("{:>17.11}", session.average_reward()), Style::Good),
+        Cell::plain(format!("ORE  (${:>.4}) over", session.average_reward() * prices.ore)),
+        Cell::new(session.passes.to_string(), Style::Highlight),
+        Cell::plain("passes\t\t\t"),
+        Cell::new(format!("[~{:.4}% of supply]", session.average_reward() * 100.0), Style::Dim),
+    ]);
+
+    let mut summary = ReportTable {
+        header: vec![
+            Cell::plain("Profit"),
+            Cell::plain("Cost"),
+            Cell::plain("Cost (Cloud)"),
+            Cell::plain("Cost (Electricity)"),
+        ],
+        rows: vec![],
+    };
+    summary.rows.push(("Tokens".to_string(), vec![
+        Cell::new(format!("{:.11} ORE", session.session_ore_mined), Style::Good),
+        Cell::new(format!("{:.6} SOL", session.session_sol_used), Style::Info),
+        Cell::plain(format!("{:.3} hours @ ${:.2}/hour", session.elapsed_hours(), inputs.running_costs.cloud_cost_per_hour)),
+        Cell::plain(format!("{:.3} kWh", session.energy_kwh)),
+    ]));
+    summary.rows.push(("In dollars".to_string(), vec![
+        Cell::plain(format!("{:.2} USD", profitability.ore_value)),
+        Cell::plain(format!("{:.2} USD", profitability.sol_cost)),
+        Cell::plain(format!("{:.2} USD", profitability.cloud_cost)),
+        Cell::plain(format!("{:.2} USD", profitability.electricity_cost)),
+    ]));
+    report.table(Some("Session Summary:".to_string()), summary);
+
+    report.line("Profitability", vec![
+        Cell::new(format!("{:>17.2}", profitability.net), Style::Profit),
+        Cell::plain("USD"),
+    ]);
+    report.line("Time", vec![
+        Cell::plain(format!("Elapsed: {}   Active mining: {} ({:.0}%)   {:.11} ORE/hour   {:.6} SOL/hour",
+            format_duration(Duration::from_secs((session.elapsed_hours() * 3600.0) as u64)),
+            format_duration(Duration::from_secs(session.mining_secs)),
+            if session.elapsed_hours() > 0.0 { session.active_hours() / session.elapsed_hours() * 100.0 } else { 0.0 },
+            session.ore_per_hour(),
+            session.sol_per_hour(),
+        )),
+    ]);
+    report.line("SOL fees", vec![
+        Cell::plain(format!("Base: {:.6} SOL   Priority: {:.6} SOL   Failed: {:.6} SOL ({} failed transactions)",
+            lamports_to_sol(session.session_fees.base),
+            lamports_to_sol(session.session_fees.priority),
+            lamports_to_sol(session.session_fees.failed),
+            session.session_fees.failed_transactions,
+        )),
+    ]);
+    if session.resets > 0 {
+        report.line("Resets", vec![
+            Cell::plain(format!("{} epoch resets   Reset fees: {:.6} SOL", session.resets, lamports_to_sol(session.reset_fees))),
+        ]);
+    }
+    report.line("Total Hashes in session", vec![
+        Cell::plain(format!("{:.1}M\t\tAverage Hashes per pass: {:.0}\t\tThreads: {}\t",
+            (session.session_hashes as f64) / 1048576.0,  // Calc Mega Hashes
+            session.average_hashes(),
+            inputs.threads,
+        )),
+        Cell::new(format!("[{:>6.2} H/s]", session.hash_rate()), Style::Dim),
+    ]);
+
+    report.table(Some(format!("Difficulties solved during {} passes:", session.passes)), difficulty_table(session));
+
+    if !session.base_rate_history.is_empty() {
+        let heading = if session.passes < 10 {
+            format!("Base Rate Change in Grains for pass {}:", session.passes)
+        } else {
+            "Base Rate Change in Grains for last 10 passes:".to_string()
+        };
+        report.table(Some(heading), base_rate_change_table(&session.base_rate_history));
+    }
+    report
+}
+
+// The table of difficulties solved during the session with the most common difficulty highlighted
+// and the tails of the distribution dimmed
+fn difficulty_table(session: &MiningSession) -> ReportTable {
+    let distribution = session.difficulty_distribution();
+    let (most_popular_difficulty, max_count) = session.most_common_difficulty().unwrap_or((0, 0));
+    let style = |highlight: bool| if highlight { Style::Highlight } else { Style::Plain };
+    let percent_style = |solves: usize, cumulative: f64, percent: f64| {
+        if solves == max_count {
+            Style::Highlight
+        } else if cumulative < 20.0 || (cumulative - percent) > 85.0 {
+            Style::Dim
+        } else {
+            Style::Plain
+        }
+    };
+    ReportTable {
+        header: vec![],
+        rows: vec![
+            ("Difficulty".to_string(), distribution.iter()
+                .map(|share| Cell::new(share.difficulty.to_string(), style(share.difficulty == most_popular_difficulty)))
+                .collect()),
+            ("Solves".to_string(), distribution.iter()
+                .map(|share| Cell::new(share.solves.to_string(), style(share.solves == max_count)))
+                .collect()),
+            ("Percentage".to_string(), distribution.iter()
+                .map(|share| Cell::new(format!("{}%", f64::trunc(share.percent) as u32), percent_style(share.solves, share.cumulative, share.percent)))
+                .collect()),
+            ("Cumulative".to_string(), distribution.iter()
+                .map(|share| Cell::new(format!("{}%", f64::trunc(share.cumulative) as u32), percent_style(share.solves, share.cumulative, share.percent)))
+                .collect()),
+        ],
+    }
+}
+
+// The last 10 base reward rates in grains along with how they changed
+fn base_rate_change_table(history: &VecDeque<BaseRateInfo>) -> ReportTable {
+    ReportTable {
+        header: (1..=history.len()).map(|pass| Cell::plain(pass.to_string())).collect(),
+        rows: vec![
+            ("Base Rate".to_string(), history.iter()
+                .map(|info| Cell::plain(((info.rate * GRAINS_PER_ORE as f64).round() as u64).to_string()))
+                .collect()),
+            ("Change".to_string(), history.iter()
+                .map(|info| match info.change {
+                    change if change > 0.0 => Cell::new(format!("{:+.1}%", change), Style::Good),
+                    change if change < 0.0 => Cell::new(format!("{:+.1}%", change), Style::Bad),
+                    _ => Cell::plain("-"),
+                })
+                .collect()),
+            ("Trend".to_string(), history.iter()
+                .map(|info| match info.trend {
+                    '▲' => Cell::new("▲", Style::Good),
+                    '▼' => Cell::new("▼", Style::Bad),
+                    _ => Cell::plain("-"),
+                })
+                .collect()),
+        ],
+    }
+}
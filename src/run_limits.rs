@@ -0,0 +1,123 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use chrono::prelude::*;
+use ore_api::consts::ONE_MINUTE;
+
+use crate::{args::MineArgs, session::MiningSession};
+
+// Why `ore mine` stopped. Each reason exits with its own code so scripts can tell them apart.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StopReason {
+    Shutdown,
+    MaxPasses,
+    MaxDuration,
+    StopTime,
+    OreTarget,
+}
+
+impl StopReason {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            StopReason::Shutdown => 0,
+            StopReason::MaxPasses => 10,
+            StopReason::MaxDuration => 11,
+            StopReason::StopTime => 12,
+            StopReason::OreTarget => 13,
+        }
+    }
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            StopReason::Shutdown => "shutdown requested",
+            StopReason::MaxPasses => "maximum passes reached",
+            StopReason::MaxDuration => "maximum duration reached",
+            StopReason::StopTime => "stop time reached",
+            StopReason::OreTarget => "session ORE target reached",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+// The limits of a bounded mining run. Time limits are checked before each pass so that a pass that
+// would finish after the limit is not started.
+pub struct RunLimits {
+    pub max_passes: Option<u32>,
+    pub max_duration: Option<Duration>,
+    pub stop_at: Option<DateTime<Local>>,
+    pub ore_target: Option<f64>,
+    started: Instant,
+}
+
+impl RunLimits {
+    pub fn new(args: &MineArgs) -> Self {
+        Self {
+            max_passes: args.max_passes,
+            max_duration: args.max_duration,
+            stop_at: args.stop_at,
+            ore_target: args.ore_target,
+            started: Instant::now(),
+        }
+    }
+
+    // The first limit that has been reached after the given number of passes in this run
+    pub fn reached(&self, passes_this_run: u32, session: &MiningSession) -> Option<StopReason> {
+        let next_pass = Duration::from_secs(ONE_MINUTE as u64);
+        if self.max_passes.is_some_and(|max| passes_this_run >= max) {
+            return Some(StopReason::MaxPasses);
+        }
+        if self.max_duration.is_some_and(|max| self.started.elapsed() + next_pass > max) {
+            return Some(StopReason::MaxDuration);
+        }
+        if self.stop_at.is_some_and(|at| Local::now() + next_pass > at) {
+            return Some(StopReason::StopTime);
+        }
+        if self.ore_target.is_some_and(|target| session.session_ore_mined >= target) {
+            return Some(StopReason::OreTarget);
+        }
+        None
+    }
+
+    // A description of the limits for the startup banner
+    pub fn describe(&self) -> Option<String> {
+        let mut limits = vec![];
+        if let Some(max) = self.max_passes {
+            limits.push(format!("{} passes", max));
+        }
+        if let Some(max) = self.max_duration {
+            limits.push(format!("{}", humantime::format_duration(max)));
+        }
+        if let Some(at) = self.stop_at {
+            limits.push(format!("{}", at.format("%H:%M:%S on %Y-%m-%d")));
+        }
+        if let Some(target) = self.ore_target {
+            limits.push(format!("{} session ORE", target));
+        }
+        if limits.is_empty() {
+            return None;
+        }
+        Some(limits.join(" or "))
+    }
+}
+
+// Parse a wall-clock stop time of "HH:MM" (the next time it occurs) or "YYYY-MM-DD HH:MM"
+pub fn parse_stop_at(value: &str) -> Result<DateTime<Local>, String> {
+    let now = Local::now();
+    let naive = if let Ok(time) = NaiveTime::parse_from_str(value, "%H:%M") {
+        let today = now.date_naive().and_time(time);
+        if today > now.naive_local() {
+            today
+        } else {
+            today + chrono::Duration::days(1)
+        }
+    } else {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M")
+            .map_err(|_| format!("invalid stop time {}: expected HH:MM or YYYY-MM-DD HH:MM", value))?
+    };
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .ok_or(format!("invalid local stop time {}", value))
+}
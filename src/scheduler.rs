@@ -0,0 +1,113 @@
+use colored::*;
+use ore_api::{
+    consts::{ONE_MINUTE, TOLERANCE},
+    state::Proof,
+};
+use solana_sdk::clock::Clock;
+
+use crate::{utils::get_clock, Miner};
+
+// The shortest pass that still has time to find and submit a hash
+const MIN_PASS_SECS: u64 = 3;
+
+// The longest pass once the liveness penalty is already reducing the reward. A short pass loses less to the penalty.
+const MAX_PENALISED_PASS_SECS: u64 = 15;
+
+// Submitting this long after the last hash leaves little or no reward once the liveness penalty is applied
+const NO_REWARD_SECS: i64 = 110;
+
+// Where a pass falls relative to the proof's last hash
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PassWindow {
+    OnTime,     // The pass can mine until the usual cutoff
+    Late,       // The usual cutoff has passed but the hash can still be submitted before the liveness penalty
+    Penalised,  // The liveness penalty will reduce the reward
+    NoReward,   // The liveness penalty will leave little or no reward
+}
+
+// How long a pass should mine for, worked out from the proof's last hash, the on-chain clock and the
+// spam & liveness thresholds of the ORE program
+pub struct PassSchedule {
+    pub window: PassWindow,
+    pub cutoff_time: u64,               // How long the pass will mine for
+    pub liveness_secs: i64,             // Seconds of hashing after which the liveness penalty reduces the reward
+    pub seconds_since_last_hash: i64,
+}
+
+impl PassSchedule {
+    pub fn new(proof: &Proof, buffer_time: u64, clock: &Clock) -> Self {
+        let seconds_since_last_hash = clock.unix_timestamp.saturating_sub(proof.last_hash_at).max(0);
+        let liveness_secs = proof.last_hash_at
+            .saturating_add(ONE_MINUTE + TOLERANCE)
+            .saturating_sub(buffer_time as i64)
+            .saturating_sub(clock.unix_timestamp);
+        let cutoff = get_cutoff(proof, buffer_time, clock);
+
+        let (window, cutoff_time) = if cutoff >= MIN_PASS_SECS as i64 {
+            (PassWindow::OnTime, cutoff as u64)
+        } else if liveness_secs >= MIN_PASS_SECS as i64 {
+            (PassWindow::Late, liveness_secs as u64)
+        } else if seconds_since_last_hash > NO_REWARD_SECS {
+            (PassWindow::NoReward, MIN_PASS_SECS)
+        } else {
+            // Every second spent mining now costs 1/60th of the reward so keep the pass short
+            let remaining = (2 * ONE_MINUTE)
+                .saturating_sub(seconds_since_last_hash)
+                .saturating_sub(buffer_time as i64);
+            (PassWindow::Penalised, remaining.clamp(MIN_PASS_SECS as i64, MAX_PENALISED_PASS_SECS as i64) as u64)
+        };
+
+        Self {
+            window,
+            cutoff_time,
+            liveness_secs,
+            seconds_since_last_hash,
+        }
+    }
+
+    // The share of the reward expected to be lost to the liveness penalty when submitting at the cutoff
+    pub fn expected_penalty(&self) -> f64 {
+        let late_secs = (self.cutoff_time as i64).saturating_sub(self.liveness_secs).max(0);
+        (late_secs as f64 / ONE_MINUTE as f64).min(1.0)
+    }
+
+    // Why the pass will mine for as long as it does, for the pass log
+    pub fn explain(&self) -> String {
+        match self.window {
+            PassWindow::OnTime => format!("{}s since last hash, mining for {}s to submit on time",
+                self.seconds_since_last_hash,
+                self.cutoff_time,
+            ).dimmed().to_string(),
+            PassWindow::Late => format!("{}s since last hash, shortened to {}s to submit before the liveness penalty",
+                self.seconds_since_last_hash,
+                self.cutoff_time,
+            ).yellow().to_string(),
+            PassWindow::Penalised => format!("{}s since last hash, shortened to {}s as the liveness penalty will reduce the reward by ~{:.0}%",
+                self.seconds_since_last_hash,
+                self.cutoff_time,
+                self.expected_penalty() * 100.0,
+            ).yellow().to_string(),
+            PassWindow::NoReward => format!("{}s since last hash, the liveness penalty will leave little or no reward so mining for {}s to reset it",
+                self.seconds_since_last_hash,
+                self.cutoff_time,
+            ).red().to_string(),
+        }
+    }
+}
+
+impl Miner {
+    // Work out the window for the next pass from the proof and the current on-chain clock
+    pub async fn schedule_pass(&self, proof: &Proof, buffer_time: u64) -> PassSchedule {
+        let clock = get_clock(&self.rpc_client).await;
+        PassSchedule::new(proof, buffer_time, &clock)
+    }
+}
+
+// Calculate how long to mine for
+// Based upon (last_hash_at time) + (1 minute) - (desired buffer_time) - (clock time)
+fn get_cutoff(proof: &Proof, buffer_time: u64, clock: &Clock) -> i64 {
+    proof.last_hash_at
+        .saturating_add(ONE_MINUTE)
+        .saturating_sub(buffer_time as i64)
+        .saturating_sub(clock.unix_timestamp)
+}
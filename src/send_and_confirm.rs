@@ -4,15 +4,15 @@ use std::fs::File;
 use std::io::{BufReader, BufRead};
 
 use colored::*;
+use serde::{Deserialize, Serialize};
 use solana_client::{
     client_error::{ClientError, ClientErrorKind, Result as ClientResult},
     rpc_config::RpcSendTransactionConfig,
 };
 use solana_program::{
     instruction::Instruction,
-    native_token::{lamports_to_sol, sol_to_lamports},
+    native_token::lamports_to_sol,
 };
-use solana_rpc_client::spinner;
 use solana_sdk::transaction::TransactionError;
 use solana_sdk::{
     commitment_config::CommitmentLevel,
@@ -20,11 +20,12 @@ use solana_sdk::{
     signature::{Signature, Signer},
     transaction::Transaction,
 };
-use solana_transaction_status::{TransactionConfirmationStatus, UiTransactionEncoding};
+use solana_transaction_status::{
+    TransactionConfirmationStatus, UiTransactionEncoding, UiTransactionStatusMeta,
+};
 
 use crate::Miner;
 
-const MIN_SOL_BALANCE: f64 = 0.005;
 
 const RPC_RETRIES: usize = 0;
 const _SIMULATION_RETRIES: usize = 4;
@@ -39,11 +40,49 @@ const CONFIRM_RETRIES: usize = 20;	// try to get transaction confirmation this m
 const CONFIRM_DELAY: u64 = 100;		// Delay in ms between reach confirmation check
 
 
+const LAMPORTS_PER_SIGNATURE: u64 = 5000;	// The base fee charged for each transaction signature
+
+
 pub enum ComputeBudget {
     Dynamic,
     Fixed(u32),
 }
 
+// The SOL fees in lamports paid by the transactions that landed on chain
+#[derive(Default, Clone, Copy, Serialize, Deserialize)]
+pub struct TransactionFees {
+    pub base: u64,					// Signature fees of successful transactions
+    pub priority: u64,				// Priority fees of successful transactions
+    pub failed: u64,				// Total fees of transactions that landed with an error
+    pub failed_transactions: u32,	// How many transactions landed with an error
+}
+
+impl TransactionFees {
+    // Add the fee from a landed transaction's meta
+    pub fn add(&mut self, meta: &UiTransactionStatusMeta) {
+        if meta.err.is_some() {
+            self.failed += meta.fee;
+            self.failed_transactions += 1;
+        } else {
+            let base = meta.fee.min(LAMPORTS_PER_SIGNATURE);
+            self.base += base;
+            self.priority += meta.fee - base;
+        }
+    }
+
+    // Add the fees of other transactions to these
+    pub fn merge(&mut self, other: &TransactionFees) {
+        self.base += other.base;
+        self.priority += other.priority;
+        self.failed += other.failed;
+        self.failed_transactions += other.failed_transactions;
+    }
+
+    pub fn total(&self) -> u64 {
+        self.base + self.priority + self.failed
+    }
+}
+
 impl Miner {
 	pub async fn send_and_confirm(
         &self,
@@ -52,6 +91,50 @@ impl Miner {
         skip_confirm: bool,
 		skip_sol_check: bool,
     ) -> ClientResult<Signature> {
+		self.send_and_confirm_tracked(ixs, compute_budget, skip_confirm, skip_sol_check).await.0
+	}
+
+	// Same as send_and_confirm but also returns the signature of every transaction that was submitted
+	// so the caller can look up the fees of attempts that failed
+	pub async fn send_and_confirm_tracked(
+        &self,
+        ixs: &[Instruction],
+        compute_budget: ComputeBudget,
+        skip_confirm: bool,
+		skip_sol_check: bool,
+    ) -> (ClientResult<Signature>, Vec<Signature>) {
+		let mut signatures = vec![];
+		let result = self.submit_transaction(ixs, compute_budget, skip_confirm, skip_sol_check, None, &mut signatures).await;
+		(result, signatures)
+	}
+
+	// Resubmit instructions whose transaction failed with a fresh blockhash and the given priority fee
+	pub async fn resubmit_tracked(
+        &self,
+        ixs: &[Instruction],
+        compute_budget: ComputeBudget,
+		priority_fee: u64,
+    ) -> (ClientResult<Signature>, Vec<Signature>) {
+		let mut signatures = vec![];
+		let result = self.submit_transaction(ixs, compute_budget, false, true, Some(priority_fee), &mut signatures).await;
+		(result, signatures)
+	}
+
+	// The priority fee for a resubmission, raised by the given percentage of the configured fee for each attempt
+	pub fn resubmit_priority_fee(&self, increase_percent: u64, attempt: u32) -> u64 {
+		let priority_fee = self.load_priority_fee();
+		priority_fee.saturating_add(priority_fee.saturating_mul(increase_percent).saturating_mul(attempt as u64) / 100)
+	}
+
+	async fn submit_transaction(
+        &self,
+        ixs: &[Instruction],
+        compute_budget: ComputeBudget,
+        skip_confirm: bool,
+		skip_sol_check: bool,
+		priority_fee: Option<u64>,
+		signatures: &mut Vec<Signature>,
+    ) -> ClientResult<Signature> {
 		
 		let signer = self.signer();
         let client = self.rpc_client.clone();
@@ -61,19 +144,19 @@ impl Miner {
         // Return error, if balance is zero
 		if !skip_sol_check {
 			if let Ok(balance) = client.get_balance(&signer.pubkey()).await {
-				if balance <= sol_to_lamports(MIN_SOL_BALANCE) {
+				if self.low_sol_balance(lamports_to_sol(balance)) {
 					panic!(
 						"{} Insufficient balance: {} SOL\nPlease top up with at least {} SOL",
 						"ERROR".bold().red(),
 						lamports_to_sol(balance),
-						MIN_SOL_BALANCE
+						self.min_sol_balance
 					);
 				}
 			}
 		}
 
-        // Read priority fee from environment file
-        let priority_fee = self.load_priority_fee();
+        // Read priority fee from environment file unless one has been given
+        let priority_fee = priority_fee.unwrap_or_else(|| self.load_priority_fee());
         println!("Priority Fee: {} lamports", priority_fee);
 
         // Set compute units
@@ -108,10 +191,11 @@ impl Miner {
             .await
             .unwrap();
         tx.sign(&[&signer], hash);		// Commenting out this line enables tesing a failed transation
+		signatures.push(tx.signatures[0]);
 
         // Submit tx
         let mut attempts = 1;
-		let progress_bar = spinner::new_progress_bar();
+		let progress_bar = self.new_progress_bar();
 		loop {
 			progress_bar.set_message(format!("[{}{}]  Attempt {}: Submitting transaction...",
 				submit_start_time.elapsed().as_secs().to_string().dimmed(),
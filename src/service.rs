@@ -1,17 +1,17 @@
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, Ordering};
 use warp::Filter;
 use serde::{Deserialize, Serialize};
-use drillx::{
-    equix::{self},
-    Hash,
-};
 use hex;
 use tokio::sync::Mutex as TokioMutex;
 use solana_rpc_client::spinner;
 use num_cpus;
-use crossbeam::thread;
-use rand::Rng;
+
+use crate::{
+    affinity::CpuPlacement,
+    args::ServiceArgs,
+    hasher::{HashJob, HashPool, HashProgress},
+};
 
 #[derive(Deserialize)]
 struct MiningRequest {
@@ -34,44 +34,45 @@ pub struct MiningResponse {
 }
 
 struct MiningState {
-    stop_flag: Arc<AtomicBool>,
-    is_mining: bool,
+    stop_flag: Arc<AtomicBool>,     // The stop flag of the latest request, each request gets its own
 }
 
-pub async fn start_service() {
+pub async fn start_service(args: ServiceArgs) {
+    let placement = match CpuPlacement::new(&args.placement) {
+        Ok(placement) => placement,
+        Err(err) => {
+            eprintln!("ERROR: Invalid CPU placement: {}", err);
+            std::process::exit(1);
+        }
+    };
+
     let mining_state = Arc::new(TokioMutex::new(MiningState {
         stop_flag: Arc::new(AtomicBool::new(false)),
-        is_mining: false,
     }));
 
+    // The hashing threads are kept for as long as the service runs and resized to each request
+    let hash_pool = Arc::new(HashPool::new(num_cpus::get() as u64, placement));
+    println!("Hashing: {}", hash_pool.describe_placement());
+
     let mine_route = warp::post()
         .and(warp::path("mine"))
         .and(warp::body::json())
         .and(warp::any().map(move || mining_state.clone()))
+        .and(warp::any().map(move || hash_pool.clone()))
         .and_then(handle_mining_request);
 
     warp::serve(mine_route).run(([0, 0, 0, 0], 3030)).await;
 }
 
-async fn handle_mining_request(req: MiningRequest, mining_state: Arc<TokioMutex<MiningState>>) -> Result<impl warp::Reply, warp::Rejection> {
-    let mut state = mining_state.lock().await;
-    
-    if state.is_mining {
-        // Stop the existing mining operation
+async fn handle_mining_request(req: MiningRequest, mining_state: Arc<TokioMutex<MiningState>>, hash_pool: Arc<HashPool>) -> Result<impl warp::Reply, warp::Rejection> {
+    // Stop the previous request, if it is still mining, and give this request a stop flag of its own. The
+    // previous search keeps its flag set until it has seen it, and this search waits for the hashing threads.
+    let stop_flag = {
+        let mut state = mining_state.lock().await;
         state.stop_flag.store(true, Ordering::SeqCst);
-        // Wait a bit for the threads to stop
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-    }
-
-    // Reset the stop flag and set is_mining to true
-    state.stop_flag.store(false, Ordering::SeqCst);
-    state.is_mining = true;
-
-    // Clone the stop_flag for use in the mining function
-    let stop_flag = state.stop_flag.clone();
-
-    // Release the lock before starting the mining operation
-    drop(state);
+        state.stop_flag = Arc::new(AtomicBool::new(false));
+        state.stop_flag.clone()
+    };
 
     let challenge_vec = hex::decode(&req.challenge).map_err(|_| warp::reject::custom(InvalidChallenge))?;
     if challenge_vec.len() != 32 {
@@ -96,113 +97,58 @@ async fn handle_mining_request(req: MiningRequest, mining_state: Arc<TokioMutex<
 
     println!("Using {} threads", thread_count);
 
-    let solution = find_hash_par(
+    // Search the requested nonce range with the same engine as the mine command
+    let job = HashJob {
         challenge,
-        req.cutoff_time,
-        req.threads,
-        req.min_difficulty,
-        req.start_nonce,
-        req.end_nonce,
-        stop_flag,
-    )
-    .await;
-
-    // Set is_mining back to false
-    mining_state.lock().await.is_mining = false;
+        nonces: req.start_nonce..req.end_nonce,
+        threads: req.threads.max(1),
+    };
+    let solution = find_hash_par(hash_pool, job, req.cutoff_time, req.min_difficulty, stop_flag).await;
 
     Ok(warp::reply::json(&solution))
 }
 
 async fn find_hash_par(
-    challenge: [u8; 32],
+    hash_pool: Arc<HashPool>,
+    job: HashJob,
     cutoff_time: u64,
-    threads: u64,
     min_difficulty: u32,
-    start_nonce: u64,
-    end_nonce: u64,
     stop_flag: Arc<AtomicBool>,
 ) -> MiningResponse {
-    let progress_bar = Arc::new(spinner::new_progress_bar());
+    let progress_bar = spinner::new_progress_bar();
     progress_bar.set_message("Mining...");
-    let total_hashes = Arc::new(AtomicU64::new(0));
-
-    let (best_nonce, best_difficulty, best_hash) = thread::scope(|s| {
-        let mut handles = Vec::with_capacity(threads as usize);
-
-        for i in 0..threads {
-            let challenge = challenge.clone();
-            let progress_bar = progress_bar.clone();
-            let stop_flag = stop_flag.clone();
-            let total_hashes = total_hashes.clone();
-
-            handles.push(s.spawn(move |_| {
-                let timer = std::time::Instant::now();
-                let range_size = (end_nonce - start_nonce) / threads;
-                let thread_start_nonce = start_nonce + range_size * i;
-                let thread_end_nonce = thread_start_nonce + range_size;
-                let mut rng = rand::thread_rng();
-                let mut memory = equix::SolverMemory::new();
-                let mut best_nonce = thread_start_nonce;
-                let mut best_difficulty = 0;
-                let mut best_hash = Hash::default();
-                let mut thread_hashes = 0;
-
-                for _ in 0..range_size {
-                    if stop_flag.load(Ordering::Relaxed) {
-                        break;
-                    }
-
-                    let nonce = rng.gen_range(thread_start_nonce..thread_end_nonce);
-                    if let Ok(hx) = drillx::hash_with_memory(
-                        &mut memory,
-                        &challenge,
-                        &nonce.to_le_bytes(),
-                    ) {
-                        let difficulty = hx.difficulty();
-                        if difficulty.gt(&best_difficulty) {
-                            best_nonce = nonce;
-                            best_difficulty = difficulty;
-                            best_hash = hx;
-                        }
-                    }
-
-                    thread_hashes += 1;
-
-                    if thread_hashes % 256 == 0 {
-                        if timer.elapsed().as_secs().ge(&cutoff_time) {
-                            if best_difficulty.ge(&min_difficulty) {
-                                break;
-                            }
-                        } else if i == 0 {
-                            progress_bar.set_message(format!(
-                                "Mining... ({} sec remaining)",
-                                cutoff_time.saturating_sub(timer.elapsed().as_secs()),
-                            ));
-                        }
-                    }
-                }
-                total_hashes.fetch_add(thread_hashes, Ordering::Relaxed);
-                (best_nonce, best_difficulty, best_hash)
-            }));
-        }
 
-        handles.into_iter().map(|h| h.join().unwrap()).max_by_key(|&(_, difficulty, _)| difficulty).unwrap()
-    }).unwrap();
+    // Stop early if a newer request arrives
+    let stop = move |progress: &HashProgress| {
+        stop_flag.load(Ordering::Relaxed)
+            || (progress.elapsed.as_secs().ge(&cutoff_time) && progress.best_difficulty.ge(&min_difficulty))
+    };
+    let show_progress = {
+        let progress_bar = progress_bar.clone();
+        move |progress: &HashProgress| {
+            progress_bar.set_message(format!(
+                "Mining... ({} sec remaining)",
+                cutoff_time.saturating_sub(progress.elapsed.as_secs()),
+            ));
+        }
+    };
+    let result = tokio::task::spawn_blocking(move || hash_pool.find_hash_par(&job, stop, show_progress))
+        .await
+        .unwrap_or_default();
 
-    let total_hashes_done = total_hashes.load(Ordering::Relaxed);
-    println!("Total hashes performed: {}", total_hashes_done);
+    println!("Total hashes performed: {} ({} difficulty checks)", result.hashes, result.checks);
 
     progress_bar.finish_with_message(format!(
         "Best hash: {} (difficulty: {})",
-        bs58::encode(best_hash.h).into_string(),
-        best_difficulty
+        bs58::encode(result.hash.h).into_string(),
+        result.difficulty
     ));
 
     MiningResponse {
-        best_nonce,
-        best_difficulty,
-        best_hash: bs58::encode(&best_hash.h).into_string(),
-        best_hash_bytes: best_hash.d.to_vec(),
+        best_nonce: result.nonce,
+        best_difficulty: result.difficulty,
+        best_hash: bs58::encode(&result.hash.h).into_string(),
+        best_hash_bytes: result.hash.d.to_vec(),
         url: "https://equix.io".to_string(),
     }
 }
@@ -0,0 +1,700 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::env;
+use std::fs::{read_to_string, rename, write};
+
+use chrono::prelude::*;
+use ore_api::{event::MineEvent, state::Proof};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use solana_program::native_token::lamports_to_sol;
+use solana_sdk::signature::Signature;
+
+use crate::{send_and_confirm::TransactionFees, utils::amount_u64_to_f64};
+
+// How often the stats are shown on screen, in passes
+const STATS_EVERY_PASSES: u32 = 5;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BaseRateInfo {
+    pub rate: f64,
+    pub change: f64,
+    pub trend: char,
+}
+
+// The outcome of a single mining pass. The hashing, submission and fees are filled in during the pass
+// and the rewards are settled from the proof fetched at the start of the following pass.
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct PassOutcome {
+    pub pass: u32,
+    pub difficulty: Option<u32>,			// The difficulty submitted, if the mine transaction landed
+    pub hashes: u64,
+    pub duration_secs: u64,
+    pub signature: Option<Signature>,		// The signature of the mine transaction that landed
+    #[serde(with = "pod_option")]
+    pub mine_event: Option<MineEvent>,		// The rewards reported by the mine transaction
+    pub fees: TransactionFees,
+    pub reward: u64,						// The reward attributed to this pass in grains
+    #[serde(skip)]
+    pub reward_source: &'static str,		// Where the reward was read from: "transaction" or "proof"
+    pub late_rewards: u64,					// Rewards of earlier submissions that landed during this pass
+    pub staked: u64,						// ORE added to the proof balance that was not a reward
+    pub claimed: u64,						// ORE removed from the proof balance
+    pub reset: bool,						// The mine transaction also reset the epoch
+    pub reset_fee: u64,						// The share of the priority fee paid for the reset's compute units
+    pub busy_secs: f64,						// Time spent hashing, the rest of the pass is idle
+    pub energy_kwh: f64,
+    pub energy_cost: f64,					// The cost of the energy in USD at the tariff of the window mined in
+}
+
+impl PassOutcome {
+    pub fn new(pass: u32) -> Self {
+        Self {
+            pass,
+            ..Default::default()
+        }
+    }
+
+    // Settle the rewards of this pass by comparing the proof at the start of the pass to the proof after it.
+    // The proof's total rewards only increase when a hash is rewarded so its delta is the exact amount mined,
+    // including any submission that landed late. Any other change to the balance is a stake or a claim.
+    pub fn settle(&mut self, proof_before: &Proof, proof_after: &Proof) {
+        let rewards = proof_after.total_rewards.saturating_sub(proof_before.total_rewards);
+        (self.reward, self.reward_source) = match self.mine_event {
+            Some(event) => (event.reward, "transaction"),
+            None => (rewards, "proof"),
+        };
+        self.late_rewards = rewards.saturating_sub(self.reward);
+
+        let balance_change = proof_after.balance as i128 - proof_before.balance as i128 - rewards as i128;
+        self.staked = balance_change.max(0) as u64;
+        self.claimed = (-balance_change).max(0) as u64;
+    }
+
+    pub fn ore_mined(&self) -> f64 {
+        amount_u64_to_f64(self.reward)
+    }
+
+    pub fn sol_used(&self) -> f64 {
+        lamports_to_sol(self.fees.total())
+    }
+}
+
+// A finished pass waiting for the proof fetched at the start of the next pass to settle its rewards. It is kept
+// in the checkpoint so that a pass finished just before the miner crashed is still settled when it restarts.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PendingPass {
+    pub outcome: PassOutcome,
+    #[serde(with = "pod")]
+    pub proof_before: Proof,                // The proof at the start of the pass
+}
+
+// The on-chain proof and mine event are plain old data, so they are kept in the checkpoint as their bytes
+mod pod {
+    use super::*;
+
+    pub fn serialize<T: bytemuck::Pod, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        bytemuck::bytes_of(value).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T: bytemuck::Pod, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+        let bytes = Vec::<u8>::deserialize(deserializer)?;
+        bytemuck::try_pod_read_unaligned(&bytes).map_err(serde::de::Error::custom)
+    }
+}
+
+mod pod_option {
+    use super::*;
+
+    pub fn serialize<T: bytemuck::Pod, S: Serializer>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error> {
+        value.as_ref().map(bytemuck::bytes_of).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T: bytemuck::Pod, D: Deserializer<'de>>(deserializer: D) -> Result<Option<T>, D::Error> {
+        Option::<Vec<u8>>::deserialize(deserializer)?
+            .map(|bytes| bytemuck::try_pod_read_unaligned(&bytes).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+// An automatic claim of the staked ORE made while mining
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ClaimEvent {
+    pub at: i64,                    // Unix timestamp of the claim
+    pub amount: u64,                // The ORE claimed in grains
+    pub to: String,                 // The wallet the ORE was claimed to
+    pub signature: String,
+    pub fees: TransactionFees,
+}
+
+// Token prices in USD
+pub struct Prices {
+    pub ore: f64,
+    pub sol: f64,
+}
+
+// The running costs of the mining rig
+pub struct RunningCosts {
+    pub cloud_cost_per_hour: f64,
+    pub wattage_idle: f64,          // Watts used by the rig when it is not hashing
+    pub wattage_busy: f64,          // Watts used by the rig when it is hashing
+    pub cost_per_kwh: f64,          // The electricity tariff when the mining schedule does not set one
+}
+
+impl RunningCosts {
+    // Read the costs from CLOUD_COST_PER_HOUR, MINER_WATTAGE_IDLE, MINER_WATTAGE_BUSY and
+    // MINER_COST_PER_KILOWATT_HOUR. The cloud cost is $1 per hour if not set, anything else not set costs nothing.
+    pub fn from_env() -> Self {
+        let read = |name: &str, default: f64| env::var(name).ok().and_then(|x| x.parse::<f64>().ok()).unwrap_or(default);
+        Self {
+            cloud_cost_per_hour: read("CLOUD_COST_PER_HOUR", 1.0),
+            wattage_idle: read("MINER_WATTAGE_IDLE", 0.0),
+            wattage_busy: read("MINER_WATTAGE_BUSY", 0.0),
+            cost_per_kwh: read("MINER_COST_PER_KILOWATT_HOUR", 0.0),
+        }
+    }
+
+    // A wallet's share of the rig's costs when several wallets are mined at once. Each wallet only pays for the
+    // power and cloud time of its share of the threads, so that the wallets together pay for the rig once.
+    pub fn share(&self, share: f64) -> Self {
+        Self {
+            cloud_cost_per_hour: self.cloud_cost_per_hour * share,
+            wattage_idle: self.wattage_idle * share,
+            wattage_busy: self.wattage_busy * share,
+            cost_per_kwh: self.cost_per_kwh,
+        }
+    }
+
+    // The energy used, in kWh, and what it cost for the given busy and idle time at the tariff, if one is given
+    pub fn energy(&self, busy_secs: f64, idle_secs: f64, tariff: Option<f64>) -> (f64, f64) {
+        let kwh = (self.wattage_busy * busy_secs + self.wattage_idle * idle_secs) / 3_600_000.0;
+        (kwh, kwh * tariff.unwrap_or(self.cost_per_kwh))
+    }
+}
+
+// The value of the session in USD
+pub struct Profitability {
+    pub ore_value: f64,
+    pub sol_cost: f64,
+    pub cloud_cost: f64,
+    pub electricity_cost: f64,
+    pub net: f64,
+}
+
+// One column of the difficulty distribution table
+pub struct DifficultyShare {
+    pub difficulty: u32,
+    pub solves: usize,
+    pub percent: f64,
+    pub cumulative: f64,
+}
+
+// The accounting for a mining session. It owns all the session counters and is fed the outcome of each pass
+// by the mining loop. The session is checkpointed to a JSON file after every pass and reloaded on startup
+// so that multi-day stats are not lost when miner.sh restarts `ore mine`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MiningSession {
+    pub wallet_name: String,
+    pub started_at: i64,                            // Unix timestamp of when the session was first started
+    pub mining_secs: u64,                           // Seconds spent mining over all processes of this session
+    #[serde(default)]
+    pub hashing_secs: f64,                          // Seconds spent hashing, the active part of each pass
+    #[serde(default)]
+    pub elapsed_secs: f64,                          // Wall-clock seconds the miner has been running, including waits and pauses
+    pub passes: u32,                                // How many passes have been completed and summarised
+    pub passes_without_rewards: u32,
+    pub session_ore_mined: f64,
+    #[serde(default)]
+    pub session_ore_staked: f64,
+    #[serde(default)]
+    pub session_ore_claimed: f64,
+    pub session_sol_used: f64,
+    #[serde(default)]
+    pub session_fees: TransactionFees,
+    pub session_hashes: u64,
+    pub difficulties_solved: BTreeMap<u32, usize>,
+    pub max_reward: f64,
+    pub max_reward_difficulty: u32,
+    pub max_reward_pass: u32,
+    pub base_rate_history: VecDeque<BaseRateInfo>,
+    #[serde(default)]
+    pub claims: Vec<ClaimEvent>,
+    #[serde(default)]
+    pub resets: u32,                                // Epoch resets included in mine transactions that landed
+    #[serde(default)]
+    pub reset_fees: u64,                            // Priority fees paid for the reset compute units in lamports
+    #[serde(default)]
+    pub energy_kwh: f64,
+    #[serde(default)]
+    pub energy_cost: f64,                           // The cost of the energy used in USD
+    #[serde(default)]
+    pub pending_pass: Option<PendingPass>,          // The last pass, until the next pass has settled its rewards
+}
+
+impl MiningSession {
+    pub fn new(wallet_name: &str) -> Self {
+        Self {
+            wallet_name: wallet_name.to_string(),
+            started_at: Local::now().timestamp(),
+            mining_secs: 0,
+            hashing_secs: 0.0,
+            elapsed_secs: 0.0,
+            passes: 0,
+            passes_without_rewards: 0,
+            session_ore_mined: 0.0,
+            session_ore_staked: 0.0,
+            session_ore_claimed: 0.0,
+            session_sol_used: 0.0,
+            session_fees: TransactionFees::default(),
+            session_hashes: 0,
+            difficulties_solved: BTreeMap::new(),
+            max_reward: 0.0,
+            max_reward_difficulty: 0,
+            max_reward_pass: 0,
+            base_rate_history: VecDeque::with_capacity(10),
+            claims: vec![],
+            resets: 0,
+            reset_fees: 0,
+            energy_kwh: 0.0,
+            energy_cost: 0.0,
+            pending_pass: None,
+        }
+    }
+
+    // Load the session for the wallet from file or start a new session if there is none
+    pub fn load_or_new(filename: &str, wallet_name: &str, new_session: bool) -> Self {
+        if new_session || filename.is_empty() {
+            return Self::new(wallet_name);
+        }
+        match read_to_string(filename) {
+            Ok(contents) => match serde_json::from_str::<MiningSession>(&contents) {
+                Ok(session) if session.wallet_name == wallet_name => session,
+                Ok(_) => {
+                    eprintln!("Warning: session file {} belongs to another wallet. Starting a new session.", filename);
+                    Self::new(wallet_name)
+                }
+                Err(err) => {
+                    // Keep the damaged file so that the session history can still be recovered by hand
+                    let backup = format!("{}.bak", filename);
+                    match rename(filename, &backup) {
+                        Ok(()) => eprintln!("Warning: failed to parse session file {}: {}. It has been kept as {}. Starting a new session.", filename, err, backup),
+                        Err(rename_err) => eprintln!("Warning: failed to parse session file {}: {}. It could not be kept as {}: {}. Starting a new session.", filename, err, backup, rename_err),
+                    }
+                    Self::new(wallet_name)
+                }
+            },
+            Err(_) => Self::new(wallet_name),
+        }
+    }
+
+    // Checkpoint the session to file. It is written to a temporary file that then replaces the session file,
+    // so a crash part way through the write leaves the previous checkpoint intact.
+    pub fn save(&self, filename: &str) {
+        if filename.is_empty() {
+            return;
+        }
+        match serde_json::to_string_pretty(self) {
+            Ok(json) => {
+                let temp_filename = format!("{}.tmp", filename);
+                if let Err(err) = write(&temp_filename, json).and_then(|_| rename(&temp_filename, filename)) {
+                    eprintln!("Error: failed to write session file {}: {}", filename, err);
+                }
+            }
+            Err(err) => eprintln!("Error: failed to serialize session: {}", err),
+        }
+    }
+
+    pub fn started_at_local(&self) -> DateTime<Local> {
+        Local.timestamp_opt(self.started_at, 0).single().unwrap_or_else(Local::now)
+    }
+
+    // The number of the next pass to mine, after the pass still waiting to be settled if there is one
+    pub fn next_pass(&self) -> u32 {
+        self.pending_pass.as_ref().map(|pending| pending.outcome.pass).unwrap_or(self.passes) + 1
+    }
+
+    // Add a settled pass to the session tallies. Returns true if the pass is the highest reward of the session.
+    pub fn record_pass(&mut self, outcome: &PassOutcome) -> bool {
+        let ore_mined = outcome.ore_mined();
+        self.passes = outcome.pass;
+        self.mining_secs += outcome.duration_secs;
+        self.hashing_secs += outcome.busy_secs;
+        self.session_hashes += outcome.hashes;
+        if let Some(difficulty) = outcome.difficulty {
+            *self.difficulties_solved.entry(difficulty).or_insert(0) += 1;
+        }
+        self.energy_kwh += outcome.energy_kwh;
+        self.energy_cost += outcome.energy_cost;
+        if outcome.reset && outcome.signature.is_some() {
+            self.resets += 1;
+            self.reset_fees += outcome.reset_fee;
+        }
+        if outcome.reward == 0 {
+            self.passes_without_rewards += 1;
+        }
+        self.session_ore_mined += ore_mined + amount_u64_to_f64(outcome.late_rewards);
+        self.session_ore_staked += amount_u64_to_f64(outcome.staked);
+        self.session_ore_claimed += amount_u64_to_f64(outcome.claimed);
+        self.session_sol_used += outcome.sol_used();
+        self.session_fees.merge(&outcome.fees);
+
+        if ore_mined > self.max_reward {
+            self.max_reward = ore_mined;
+            self.max_reward_difficulty = outcome.difficulty.unwrap_or(0);
+            self.max_reward_pass = outcome.pass;
+            return true;
+        }
+        false
+    }
+
+    // Add the energy used while mining was paused, outside the mining schedule or waiting for the wallet to be funded
+    pub fn record_pause(&mut self, energy_kwh: f64, energy_cost: f64) {
+        self.energy_kwh += energy_kwh;
+        self.energy_cost += energy_cost;
+    }
+
+    // Add an automatic claim to the session. The fees it paid are part of the session's SOL costs.
+    pub fn record_claim(&mut self, claim: ClaimEvent) {
+        self.session_ore_claimed += amount_u64_to_f64(claim.amount);
+        self.record_fees(&claim.fees);
+        self.claims.push(claim);
+    }
+
+    // Add the fees of transactions made outside of a pass, such as an automatic claim that failed
+    pub fn record_fees(&mut self, fees: &TransactionFees) {
+        self.session_sol_used += lamports_to_sol(fees.total());
+        self.session_fees.merge(fees);
+    }
+
+    // Keep the last 10 base reward rates along with how they have changed
+    pub fn record_base_rate(&mut self, new_rate: f64) {
+        let (change, trend) = if let Some(last_rate) = self.base_rate_history.back() {
+            // A change from a zero rate has no percentage so it only shows the trend
+            let change_percentage = if last_rate.rate == 0.0 {
+                0.0
+            } else {
+                (new_rate - last_rate.rate) / last_rate.rate * 100.0
+            };
+            let trend = if new_rate > last_rate.rate { '▲' } else if new_rate < last_rate.rate { '▼' } else { '─' };
+            (change_percentage, trend)
+        } else {
+            (0.0, '─')
+        };
+
+        self.base_rate_history.push_back(BaseRateInfo {
+            rate: new_rate,
+            change,
+            trend,
+        });
+
+        if self.base_rate_history.len() > 10 {
+            self.base_rate_history.pop_front();
+        }
+    }
+
+    // The stats are shown on screen after every STATS_EVERY_PASSES passes
+    pub fn stats_pass(&self) -> bool {
+        self.passes % STATS_EVERY_PASSES == 0
+    }
+
+    pub fn average_reward(&self) -> f64 {
+        if self.passes == 0 {
+            return 0.0;
+        }
+        self.session_ore_mined / self.passes as f64
+    }
+
+    pub fn average_hashes(&self) -> f64 {
+        if self.passes == 0 {
+            return 0.0;
+        }
+        self.session_hashes as f64 / self.passes as f64
+    }
+
+    // Add the wall-clock time since the last checkpoint of the session
+    pub fn record_elapsed(&mut self, secs: f64) {
+        self.elapsed_secs += secs;
+    }
+
+    // Hashes per second while hashing. Sessions saved before hashing time was recorded use the pass durations.
+    pub fn hash_rate(&self) -> f64 {
+        let secs = if self.hashing_secs > 0.0 { self.hashing_secs } else { self.mining_secs as f64 };
+        if secs == 0.0 {
+            return 0.0;
+        }
+        self.session_hashes as f64 / secs
+    }
+
+    // How long the miner has been running, including waiting for SOL and pauses outside the mining schedule
+    pub fn elapsed_hours(&self) -> f64 {
+        self.elapsed_secs.max(self.mining_secs as f64) / 3600.0
+    }
+
+    // How long the miner has spent in mining passes
+    pub fn active_hours(&self) -> f64 {
+        self.mining_secs as f64 / 3600.0
+    }
+
+    pub fn ore_per_hour(&self) -> f64 {
+        per_hour(self.session_ore_mined, self.elapsed_hours())
+    }
+
+    pub fn sol_per_hour(&self) -> f64 {
+        per_hour(self.session_sol_used, self.elapsed_hours())
+    }
+
+    pub fn profitability(&self, prices: &Prices, costs: &RunningCosts) -> Profitability {
+        let ore_value = self.session_ore_mined * prices.ore;
+        let sol_cost = self.session_sol_used * prices.sol;
+        // A cloud instance is paid for while it is running, whether it is mining or not
+        let cloud_cost = costs.cloud_cost_per_hour * self.elapsed_hours();
+        Profitability {
+            ore_value,
+            sol_cost,
+            cloud_cost,
+            electricity_cost: self.energy_cost,
+            net: ore_value - sol_cost - cloud_cost - self.energy_cost,
+        }
+    }
+
+    // The share of the solves for each difficulty level, in increasing order of difficulty
+    pub fn difficulty_distribution(&self) -> Vec<DifficultyShare> {
+        let total_solves: usize = self.difficulties_solved.values().sum();
+        let mut cumulative = 0.0;
+        self.difficulties_solved
+            .iter()
+            .map(|(&difficulty, &solves)| {
+                let percent = solves as f64 * 100.0 / total_solves as f64;
+                cumulative += percent;
+                DifficultyShare {
+                    difficulty,
+                    solves,
+                    percent,
+                    cumulative,
+                }
+            })
+            .collect()
+    }
+
+    // The most commonly solved difficulty, preferring the higher difficulty when counts are tied
+    pub fn most_common_difficulty(&self) -> Option<(u32, usize)> {
+        self.difficulties_solved
+            .iter()
+            .max_by_key(|(&difficulty, &solves)| (solves, difficulty))
+            .map(|(&difficulty, &solves)| (difficulty, solves))
+    }
+
+    // How many passes solved at least the given difficulty
+    pub fn difficulty_hits(&self, min_difficulty: u32) -> usize {
+        self.difficulties_solved
+            .range(min_difficulty..)
+            .map(|(_, &count)| count)
+            .sum()
+    }
+}
+
+fn per_hour(amount: f64, hours: f64) -> f64 {
+    if hours == 0.0 {
+        return 0.0;
+    }
+    amount / hours
+}
+
+// The default session filename is keyed by the wallet name so each wallet keeps its own session
+pub fn session_filename(wallet_name: &str) -> String {
+    format!("./ore_session_{}.json", wallet_name.replace(' ', "_"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::pubkey::Pubkey;
+
+    const ORE: u64 = 100_000_000_000;
+
+    fn proof(balance: u64, total_rewards: u64) -> Proof {
+        Proof {
+            authority: Pubkey::default(),
+            balance,
+            challenge: [0; 32],
+            last_hash: [0; 32],
+            last_hash_at: 0,
+            last_stake_at: 0,
+            miner: Pubkey::default(),
+            total_hashes: 0,
+            total_rewards,
+        }
+    }
+
+    fn mine_event(reward: u64) -> MineEvent {
+        MineEvent {
+            difficulty: 16,
+            reward,
+            timing: 0,
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "expected {} but got {}", expected, actual);
+    }
+
+    #[test]
+    fn settle_uses_the_mine_event_reward() {
+        let mut outcome = PassOutcome::new(1);
+        outcome.mine_event = Some(mine_event(2 * ORE));
+        outcome.settle(&proof(10 * ORE, 100 * ORE), &proof(13 * ORE, 103 * ORE));
+        assert_eq!(outcome.reward, 2 * ORE);
+        assert_eq!(outcome.reward_source, "transaction");
+        // The rest of the rewards belong to an earlier submission that landed late
+        assert_eq!(outcome.late_rewards, ORE);
+        assert_eq!(outcome.staked, 0);
+        assert_eq!(outcome.claimed, 0);
+    }
+
+    #[test]
+    fn settle_falls_back_to_the_proof_rewards() {
+        let mut outcome = PassOutcome::new(1);
+        outcome.settle(&proof(10 * ORE, 100 * ORE), &proof(13 * ORE, 103 * ORE));
+        assert_eq!(outcome.reward, 3 * ORE);
+        assert_eq!(outcome.reward_source, "proof");
+        assert_eq!(outcome.late_rewards, 0);
+    }
+
+    #[test]
+    fn settle_splits_the_balance_change_into_stakes_and_claims() {
+        let mut staked = PassOutcome::new(1);
+        staked.settle(&proof(10 * ORE, 100 * ORE), &proof(16 * ORE, 101 * ORE));
+        assert_eq!(staked.reward, ORE);
+        assert_eq!(staked.staked, 5 * ORE);
+        assert_eq!(staked.claimed, 0);
+
+        let mut claimed = PassOutcome::new(2);
+        claimed.settle(&proof(10 * ORE, 100 * ORE), &proof(7 * ORE, 101 * ORE));
+        assert_eq!(claimed.reward, ORE);
+        assert_eq!(claimed.staked, 0);
+        assert_eq!(claimed.claimed, 4 * ORE);
+    }
+
+    #[test]
+    fn pending_pass_survives_a_restart() {
+        let mut session = MiningSession::new("test");
+        session.passes = 4;
+        let mut outcome = PassOutcome::new(5);
+        outcome.mine_event = Some(mine_event(2 * ORE));
+        outcome.hashes = 1000;
+        session.pending_pass = Some(PendingPass { outcome, proof_before: proof(10 * ORE, 100 * ORE) });
+
+        let json = serde_json::to_string(&session).unwrap();
+        let restored: MiningSession = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.next_pass(), 6);
+        let PendingPass { mut outcome, proof_before } = restored.pending_pass.unwrap();
+        assert_eq!(outcome.hashes, 1000);
+        outcome.settle(&proof_before, &proof(12 * ORE, 102 * ORE));
+        assert_eq!(outcome.reward, 2 * ORE);
+        assert_eq!(outcome.reward_source, "transaction");
+    }
+
+    #[test]
+    fn record_pass_tracks_the_max_reward_and_passes_without_rewards() {
+        let mut session = MiningSession::new("test");
+
+        let mut first = PassOutcome::new(1);
+        first.difficulty = Some(14);
+        first.reward = ORE / 10;
+        assert!(session.record_pass(&first));
+
+        let mut empty = PassOutcome::new(2);
+        empty.difficulty = Some(12);
+        assert!(!session.record_pass(&empty));
+
+        let mut best = PassOutcome::new(3);
+        best.difficulty = Some(18);
+        best.reward = ORE / 2;
+        best.late_rewards = ORE / 10;
+        assert!(session.record_pass(&best));
+
+        assert_eq!(session.passes, 3);
+        assert_eq!(session.passes_without_rewards, 1);
+        assert_close(session.max_reward, 0.5);
+        assert_eq!(session.max_reward_difficulty, 18);
+        assert_eq!(session.max_reward_pass, 3);
+        assert_close(session.session_ore_mined, 0.7);
+        assert_eq!(session.difficulties_solved.len(), 3);
+    }
+
+    #[test]
+    fn record_fees_counts_the_fees_of_a_failed_claim() {
+        let mut session = MiningSession::new("test");
+        let fees = TransactionFees {
+            failed: 15_000,
+            failed_transactions: 3,
+            ..Default::default()
+        };
+        session.record_fees(&fees);
+        session.record_fees(&fees);
+        assert_close(session.session_sol_used, 0.00003);
+        assert_eq!(session.session_fees.failed_transactions, 6);
+        assert!(session.claims.is_empty());
+    }
+
+    #[test]
+    fn difficulty_distribution_has_percentages_and_cumulative_values() {
+        let mut session = MiningSession::new("test");
+        session.difficulties_solved = BTreeMap::from([(12, 1), (14, 2), (16, 1)]);
+        let distribution = session.difficulty_distribution();
+        let shares: Vec<(u32, usize, f64, f64)> = distribution
+            .iter()
+            .map(|share| (share.difficulty, share.solves, share.percent, share.cumulative))
+            .collect();
+        assert_eq!(shares, vec![(12, 1, 25.0, 25.0), (14, 2, 50.0, 75.0), (16, 1, 25.0, 100.0)]);
+    }
+
+    #[test]
+    fn record_base_rate_handles_a_zero_rate() {
+        let mut session = MiningSession::new("test");
+        session.record_base_rate(0.0);
+        session.record_base_rate(0.5);
+        let latest = session.base_rate_history.back().unwrap();
+        assert_eq!(latest.change, 0.0);
+        assert_eq!(latest.trend, '▲');
+        session.record_base_rate(0.25);
+        assert_close(session.base_rate_history.back().unwrap().change, -50.0);
+    }
+
+    #[test]
+    fn profitability_takes_every_cost_off_the_ore_value() {
+        let mut session = MiningSession::new("test");
+        session.session_ore_mined = 2.0;
+        session.session_sol_used = 0.1;
+        session.elapsed_secs = 7200.0;
+        session.energy_cost = 0.25;
+        let costs = RunningCosts {
+            cloud_cost_per_hour: 0.5,
+            wattage_idle: 0.0,
+            wattage_busy: 0.0,
+            cost_per_kwh: 0.0,
+        };
+        let profitability = session.profitability(&Prices { ore: 30.0, sol: 150.0 }, &costs);
+        assert_close(profitability.ore_value, 60.0);
+        assert_close(profitability.sol_cost, 15.0);
+        assert_close(profitability.cloud_cost, 1.0);
+        assert_close(profitability.electricity_cost, 0.25);
+        assert_close(profitability.net, 43.75);
+    }
+
+    #[test]
+    fn running_costs_share_splits_the_rig_between_wallets() {
+        let costs = RunningCosts {
+            cloud_cost_per_hour: 1.0,
+            wattage_idle: 20.0,
+            wattage_busy: 100.0,
+            cost_per_kwh: 0.3,
+        };
+        let (rig_kwh, rig_cost) = costs.energy(1800.0, 1800.0, None);
+        let wallets = [costs.share(0.75), costs.share(0.25)];
+        let energy: Vec<(f64, f64)> = wallets.iter().map(|wallet| wallet.energy(1800.0, 1800.0, None)).collect();
+        assert_close(energy[0].0 + energy[1].0, rig_kwh);
+        assert_close(energy[0].1 + energy[1].1, rig_cost);
+        assert_close(energy[0].0, 0.045);
+        assert_close(wallets[0].cloud_cost_per_hour + wallets[1].cloud_cost_per_hour, 1.0);
+        assert_close(wallets[1].cost_per_kwh, 0.3);
+    }
+}
@@ -0,0 +1,60 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use colored::*;
+
+// The exit code used when a second signal stops the miner immediately
+pub const EXIT_CODE_INTERRUPTED: i32 = 130;
+
+// Listens for SIGINT (Ctrl+C) and SIGTERM. The first signal asks the miner to finish the current pass,
+// submit its hash and write the final stats. A second signal exits straight away.
+#[derive(Clone)]
+pub struct Shutdown {
+    requested: Arc<AtomicBool>,
+}
+
+impl Shutdown {
+    pub fn listen() -> Self {
+        let shutdown = Self {
+            requested: Arc::new(AtomicBool::new(false)),
+        };
+        let requested = shutdown.requested.clone();
+        tokio::spawn(async move {
+            loop {
+                let signal = wait_for_signal().await;
+                if requested.swap(true, Ordering::SeqCst) {
+                    eprintln!("\n{} received again. Exiting immediately.", signal);
+                    std::process::exit(EXIT_CODE_INTERRUPTED);
+                }
+                println!("\n{}",
+                    format!("{} received. Finishing the current pass and writing the final stats. Press Ctrl+C again to exit immediately.", signal)
+                        .bold()
+                        .yellow(),
+                );
+            }
+        });
+        shutdown
+    }
+
+    pub fn requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() -> &'static str {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut sigterm = signal(SignalKind::terminate()).expect("failed to listen for SIGTERM");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => "SIGINT",
+        _ = sigterm.recv() => "SIGTERM",
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() -> &'static str {
+    let _ = tokio::signal::ctrl_c().await;
+    "Ctrl+C"
+}
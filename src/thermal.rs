@@ -0,0 +1,108 @@
+use std::collections::VecDeque;
+
+use chrono::prelude::*;
+use serde::Serialize;
+
+use crate::args::MineArgs;
+
+// How long to pause before a pass when the CPU is still too hot while mining on a single thread
+pub const THERMAL_PAUSE_SECS: u64 = 15;
+
+// How many throttle events are kept for the stats
+const MAX_THERMAL_EVENTS: usize = 10;
+
+// A change to the threads or a pause made because of the CPU temperature
+#[derive(Serialize, Clone)]
+pub struct ThermalEvent {
+    pub at: String,
+    pub cpu_temp: f32,
+    pub from_threads: u64,
+    pub to_threads: u64,
+    pub pause_secs: u64,
+}
+
+impl ThermalEvent {
+    pub fn describe(&self) -> String {
+        if self.pause_secs > 0 {
+            format!("CPU at {}°C, pausing for {}s before mining on {} thread", self.cpu_temp, self.pause_secs, self.to_threads)
+        } else if self.to_threads < self.from_threads {
+            format!("CPU at {}°C, reducing threads from {} to {}", self.cpu_temp, self.from_threads, self.to_threads)
+        } else {
+            format!("CPU cooled to {}°C, restoring threads from {} to {}", self.cpu_temp, self.from_threads, self.to_threads)
+        }
+    }
+}
+
+// What the throttle decided for a pass
+pub struct ThermalDecision {
+    pub threads: u64,
+    pub pause_secs: u64,
+    pub event: Option<ThermalEvent>,
+}
+
+// Lowers the hashing threads while the CPU is above --max-cpu-temp, halving them each pass until a single thread
+// is left and then pausing before each pass. Once the CPU has cooled to --resume-cpu-temp the threads are doubled
+// each pass until they are back to the configured number.
+pub struct ThermalThrottle {
+    pub max_temp: Option<f32>,
+    pub resume_temp: f32,
+    thread_cap: Option<u64>,        // The most threads to mine with while throttled
+    pub events: VecDeque<ThermalEvent>,
+    pub throttle_count: u32,
+}
+
+impl ThermalThrottle {
+    pub fn new(args: &MineArgs) -> Self {
+        Self {
+            max_temp: args.max_cpu_temp,
+            resume_temp: args.resume_cpu_temp.or(args.max_cpu_temp.map(|t| t - 5.0)).unwrap_or(0.0),
+            thread_cap: None,
+            events: VecDeque::new(),
+            throttle_count: 0,
+        }
+    }
+
+    pub fn throttled(&self) -> bool {
+        self.thread_cap.is_some()
+    }
+
+    // Decide the threads for the next pass from the CPU temperature. A temperature that could not be read
+    // leaves the threads as they are.
+    pub fn decide(&mut self, cpu_temp: Option<f32>, threads: u64) -> ThermalDecision {
+        let current = self.thread_cap.map_or(threads, |cap| cap.min(threads));
+        let mut decision = ThermalDecision { threads: current, pause_secs: 0, event: None };
+        let (Some(max_temp), Some(cpu_temp)) = (self.max_temp, cpu_temp) else {
+            return decision;
+        };
+
+        if cpu_temp >= max_temp {
+            if current > 1 {
+                decision.threads = (current / 2).max(1);
+                self.thread_cap = Some(decision.threads);
+            } else {
+                self.thread_cap = Some(1);
+                decision.pause_secs = THERMAL_PAUSE_SECS;
+            }
+            self.throttle_count += 1;
+        } else if cpu_temp <= self.resume_temp && self.thread_cap.is_some() {
+            decision.threads = (current * 2).min(threads);
+            self.thread_cap = if decision.threads >= threads { None } else { Some(decision.threads) };
+        }
+
+        if decision.threads != current || decision.pause_secs > 0 {
+            let event = ThermalEvent {
+                at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+                cpu_temp,
+                from_threads: current,
+                to_threads: decision.threads,
+                pause_secs: decision.pause_secs,
+            };
+            self.events.push_back(event.clone());
+            if self.events.len() > MAX_THERMAL_EVENTS {
+                self.events.pop_front();
+            }
+            decision.event = Some(event);
+        }
+        decision
+    }
+}
@@ -0,0 +1,179 @@
+use std::env;
+use std::time::{Duration, Instant};
+
+use chrono::prelude::*;
+use colored::*;
+use humantime::format_duration;
+
+use crate::{shutdown::Shutdown, Miner};
+
+// How far ahead to look for the start of the next window
+const LOOKAHEAD_MINUTES: i64 = 8 * 24 * 60;
+
+// A period of the week when mining is allowed, e.g. "Mon-Fri 23:00-07:00 threads=8 tariff=0.12".
+// A window that ends before it starts runs past midnight into the next day.
+pub struct MiningWindow {
+    pub description: String,
+    days: [bool; 7],                // Monday first
+    start: NaiveTime,
+    end: NaiveTime,
+    pub threads: Option<u64>,       // The threads to mine with during the window instead of --threads
+    pub tariff: Option<f64>,        // The electricity cost per kWh during the window
+}
+
+impl MiningWindow {
+    fn contains(&self, at: NaiveDateTime) -> bool {
+        let day = at.weekday().num_days_from_monday() as usize;
+        let previous_day = (day + 6) % 7;
+        let time = at.time();
+        if self.start < self.end {
+            self.days[day] && time >= self.start && time < self.end
+        } else {
+            (self.days[day] && time >= self.start) || (self.days[previous_day] && time < self.end)
+        }
+    }
+}
+
+// When `ore mine` is allowed to mine, read from MINER_SCHEDULE. Windows are separated by semicolons and the
+// first window that contains the current local time is used.
+pub struct MiningTimetable {
+    pub windows: Vec<MiningWindow>,
+}
+
+impl MiningTimetable {
+    pub fn window_at(&self, at: DateTime<Local>) -> Option<&MiningWindow> {
+        self.windows.iter().find(|window| window.contains(at.naive_local()))
+    }
+
+    // The start of the next window, checked a minute at a time
+    pub fn next_start(&self, from: DateTime<Local>) -> Option<DateTime<Local>> {
+        let from = from.with_second(0).unwrap_or(from).with_nanosecond(0).unwrap_or(from);
+        (1..=LOOKAHEAD_MINUTES)
+            .map(|minutes| from + chrono::Duration::minutes(minutes))
+            .find(|at| self.window_at(*at).is_some())
+    }
+
+    pub fn describe(&self) -> String {
+        self.windows.iter().map(|window| window.description.clone()).collect::<Vec<_>>().join("; ")
+    }
+}
+
+// Read the mining schedule from MINER_SCHEDULE. Without one the miner mines all the time.
+pub fn timetable_from_env() -> Result<Option<MiningTimetable>, String> {
+    let schedule = env::var("MINER_SCHEDULE").unwrap_or_default();
+    if schedule.trim().is_empty() {
+        return Ok(None);
+    }
+    let windows = schedule
+        .split(';')
+        .map(str::trim)
+        .filter(|window| !window.is_empty())
+        .map(parse_window)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Some(MiningTimetable { windows }))
+}
+
+// Parse "[DAYS] HH:MM-HH:MM [threads=N] [tariff=COST]". DAYS is a comma separated list of days or day ranges
+// such as Mon-Fri, or daily, weekdays or weekends. Without DAYS the window applies every day.
+fn parse_window(text: &str) -> Result<MiningWindow, String> {
+    let mut fields = text.split_whitespace().peekable();
+    let days = match fields.peek() {
+        Some(field) if !field.contains(':') => parse_days(fields.next().unwrap())?,
+        _ => [true; 7],
+    };
+    let times = fields.next().ok_or(format!("missing time range in schedule window {}", text))?;
+    let (start, end) = times.split_once('-').ok_or(format!("invalid time range {}: expected HH:MM-HH:MM", times))?;
+    let mut window = MiningWindow {
+        description: text.to_string(),
+        days,
+        start: parse_time(start)?,
+        end: parse_time(end)?,
+        threads: None,
+        tariff: None,
+    };
+    for field in fields {
+        match field.split_once('=') {
+            Some(("threads", value)) => {
+                window.threads = Some(value.parse::<u64>().map_err(|_| format!("invalid thread count {}", value))?.max(1));
+            }
+            Some(("tariff", value)) => {
+                window.tariff = Some(value.parse::<f64>().map_err(|_| format!("invalid tariff {}", value))?);
+            }
+            _ => return Err(format!("unknown option {} in schedule window {}", field, text)),
+        }
+    }
+    Ok(window)
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime, String> {
+    // 24:00 is the end of the day, the same as the following midnight
+    if value == "24:00" {
+        return Ok(NaiveTime::MIN);
+    }
+    NaiveTime::parse_from_str(value, "%H:%M").map_err(|_| format!("invalid time {}: expected HH:MM", value))
+}
+
+fn parse_days(value: &str) -> Result<[bool; 7], String> {
+    let mut days = [false; 7];
+    for part in value.split(',') {
+        match part.to_lowercase().as_str() {
+            "daily" => days = [true; 7],
+            "weekdays" => days[0..5].iter_mut().for_each(|d| *d = true),
+            "weekends" => days[5..7].iter_mut().for_each(|d| *d = true),
+            range => {
+                let (first, last) = range.split_once('-').unwrap_or((range, range));
+                let first = parse_day(first)?;
+                let last = parse_day(last)?;
+                let mut day = first;
+                loop {
+                    days[day] = true;
+                    if day == last {
+                        break;
+                    }
+                    day = (day + 1) % 7;
+                }
+            }
+        }
+    }
+    Ok(days)
+}
+
+fn parse_day(value: &str) -> Result<usize, String> {
+    value
+        .parse::<Weekday>()
+        .map(|day| day.num_days_from_monday() as usize)
+        .map_err(|_| format!("invalid day {}", value))
+}
+
+impl Miner {
+    // Wait for the next mining window, showing when mining will resume. Returns early if the miner is asked
+    // to stop or the run should end while paused.
+    pub async fn wait_for_window(&self, timetable: &MiningTimetable, shutdown: &Shutdown, stop: impl Fn() -> bool) {
+        let pause_start_time = Instant::now();
+        let next_start = timetable.next_start(Local::now());
+        let progress_bar = self.new_progress_bar();
+        while timetable.window_at(Local::now()).is_none() {
+            if shutdown.requested() || stop() {
+                progress_bar.finish_with_message("Stopped waiting for the next mining window".yellow().to_string());
+                return;
+            }
+            let resumes = match next_start {
+                Some(at) => format!("Mining resumes at {} (in {})",
+                    at.format("%H:%M on %Y-%m-%d"),
+                    format_duration(Duration::from_secs(at.signed_duration_since(Local::now()).num_seconds().max(0) as u64)),
+                ),
+                None => "No mining window in the next week".to_string(),
+            };
+            progress_bar.set_message(format!("[{}] {} {}",
+                format_duration(Duration::from_secs(pause_start_time.elapsed().as_secs())).to_string().dimmed(),
+                "Paused outside the mining schedule.".yellow(),
+                resumes,
+            ));
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        progress_bar.finish_with_message(format!("{} after pausing for {}",
+            "Mining window started".green(),
+            format_duration(Duration::from_secs(pause_start_time.elapsed().as_secs())),
+        ));
+    }
+}
@@ -1,18 +1,29 @@
 use std::io::Read;
+use std::str::FromStr;
+use std::time::Duration;
 
+use base64::{prelude::BASE64_STANDARD, Engine};
 use cached::proc_macro::cached;
 use ore_api::{
     consts::{
         CONFIG_ADDRESS, MINT_ADDRESS, PROOF, TOKEN_DECIMALS, TOKEN_DECIMALS_V1, TREASURY_ADDRESS,
     },
+    event::MineEvent,
     state::{Config, Proof, Treasury},
 };
 use ore_utils::AccountDeserialize;
-use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_config::RpcTransactionConfig};
 use solana_program::{pubkey::Pubkey, sysvar};
-use solana_sdk::clock::Clock;
+use solana_sdk::{clock::Clock, commitment_config::CommitmentConfig, signature::Signature};
+use solana_transaction_status::{
+    option_serializer::OptionSerializer, UiTransactionEncoding, UiTransactionStatusMeta,
+};
 use spl_associated_token_account::get_associated_token_address;
 
+// How many times to look up a confirmed transaction before giving up on it
+const TRANSACTION_LOOKUP_RETRIES: usize = 5;
+const TRANSACTION_LOOKUP_DELAY: u64 = 500;
+
 pub async fn _get_treasury(client: &RpcClient) -> Treasury {
     let data = client
         .get_account_data(&TREASURY_ADDRESS)
@@ -50,6 +61,50 @@ pub async fn get_clock(client: &RpcClient) -> Clock {
     bincode::deserialize::<Clock>(&data).expect("Failed to deserialize clock")
 }
 
+// Fetch the status meta of a confirmed transaction, retrying while the RPC catches up with it
+pub async fn get_transaction_meta(
+    client: &RpcClient,
+    signature: &Signature,
+) -> Option<UiTransactionStatusMeta> {
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Json),
+        commitment: Some(CommitmentConfig::confirmed()),
+        max_supported_transaction_version: Some(0),
+    };
+    for _ in 0..TRANSACTION_LOOKUP_RETRIES {
+        if let Ok(tx) = client.get_transaction_with_config(signature, config).await {
+            return tx.transaction.meta;
+        }
+        tokio::time::sleep(Duration::from_millis(TRANSACTION_LOOKUP_DELAY)).await;
+    }
+    None
+}
+
+// Fetch the status meta of a transaction only if it has landed on chain
+pub async fn get_landed_transaction_meta(
+    client: &RpcClient,
+    signature: &Signature,
+) -> Option<UiTransactionStatusMeta> {
+    let statuses = client
+        .get_signature_statuses_with_history(&[*signature])
+        .await
+        .ok()?;
+    statuses.value.first()?.as_ref()?;
+    get_transaction_meta(client, signature).await
+}
+
+// Parse the MineEvent the ORE program sets as return data of a mine instruction
+pub fn parse_mine_event(meta: &UiTransactionStatusMeta) -> Option<MineEvent> {
+    let OptionSerializer::Some(return_data) = &meta.return_data else {
+        return None;
+    };
+    if Pubkey::from_str(&return_data.program_id).ok()? != ore_api::ID {
+        return None;
+    }
+    let data = BASE64_STANDARD.decode(&return_data.data.0).ok()?;
+    bytemuck::try_pod_read_unaligned::<MineEvent>(&data).ok()
+}
+
 pub fn amount_u64_to_string(amount: u64) -> String {
     amount_u64_to_f64(amount).to_string()
 }
@@ -0,0 +1,259 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::prelude::*;
+use colored::*;
+use futures::stream::{FuturesUnordered, StreamExt};
+use solana_sdk::signature::{read_keypair_file, Signer};
+
+use crate::{
+    args::MineArgs,
+    report::{Cell, ReportFormat, ReportTable, StatsReport, Style},
+    run_limits::StopReason,
+    session::{session_filename, MiningSession},
+    shutdown::Shutdown,
+    Miner,
+};
+
+// How often the combined stats of all wallets are shown when mining with several wallets
+const COMBINED_STATS_SECS: u64 = 300;
+
+// A wallet mined by this process, with where its session and stats are kept and its share of the threads
+#[derive(Clone)]
+pub struct WalletProfile {
+    pub name: String,
+    pub keypair_filepath: String,
+    pub session_key: String,
+    pub session_file: String,
+    pub stats_logfile: String,
+    pub threads: u64,
+    pub first_thread: u64,          // Where this wallet's threads start among all of the process's threads
+    pub cost_share: f64,            // The share of the rig's power and cloud costs paid by this wallet
+    pub label: String,              // Prefixed to the pass log to tell the wallets apart, empty for a single wallet
+}
+
+// The wallets to mine. Without --keypairs this is the single wallet from --keypair, named by WALLET_NAME as before.
+// Each of --keypairs is a keypair path, optionally prefixed with NAME=, and the threads are split between them.
+pub fn wallet_profiles(args: &MineArgs, default_keypair: Option<String>) -> Result<Vec<WalletProfile>, String> {
+    let stats_logfile = env::var("STATS_LOGFILE").unwrap_or("".to_string());
+    if args.keypairs.is_empty() {
+        let keypair_filepath = default_keypair.ok_or("no keypair provided")?;
+        let wallet_name = env::var("WALLET_NAME").ok();
+        let session_key = match wallet_name.clone() {
+            Some(name) => name,
+            None => wallet_pubkey(&keypair_filepath)?,
+        };
+        return Ok(vec![WalletProfile {
+            name: wallet_name.unwrap_or("Unnamed Wallet".to_string()),
+            session_file: env::var("SESSION_STATEFILE").unwrap_or(session_filename(&session_key)),
+            session_key,
+            keypair_filepath,
+            stats_logfile,
+            threads: args.threads,
+            first_thread: 0,
+            cost_share: 1.0,
+            label: String::new(),
+        }]);
+    }
+
+    let shares = split_threads(args.threads, args.keypairs.len());
+    let total_threads: u64 = shares.iter().sum();
+    let first_threads: Vec<u64> = shares.iter().scan(0, |first, threads| {
+        let start = *first;
+        *first += threads;
+        Some(start)
+    }).collect();
+    args.keypairs
+        .iter()
+        .zip(shares)
+        .zip(first_threads)
+        .map(|((entry, threads), first_thread)| {
+            let (name, keypair_filepath) = match entry.split_once('=') {
+                Some((name, path)) => (name.to_string(), expand_home(path)),
+                None => {
+                    let path = expand_home(entry);
+                    (Path::new(&path).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or(entry.clone()), path)
+                }
+            };
+            // Each wallet keeps its own session, keyed by its public key so that names can be changed
+            let session_key = wallet_pubkey(&keypair_filepath).map_err(|err| format!("{} (--keypairs entry {})", err, entry))?;
+            Ok(WalletProfile {
+                label: format!("[{}] ", name),
+                session_file: session_filename(&session_key),
+                session_key,
+                stats_logfile: if stats_logfile.is_empty() { stats_logfile.clone() } else { format!("{}.{}", stats_logfile, name) },
+                name,
+                keypair_filepath,
+                cost_share: threads as f64 / total_threads as f64,
+                threads,
+                first_thread,
+            })
+        })
+        .collect()
+}
+
+// Expand a leading ~ in a keypair path. The shell only expands it at the start of a word, so in a list such as
+// main=~/id1.json,alt=~/id2.json none of the paths would be expanded.
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{}/{}", home.trim_end_matches('/'), rest),
+        _ => path.to_string(),
+    }
+}
+
+// Split the thread budget as evenly as possible, giving every wallet at least one thread
+fn split_threads(threads: u64, wallets: usize) -> Vec<u64> {
+    let wallets = wallets as u64;
+    (0..wallets)
+        .map(|i| (threads / wallets + u64::from(i < threads % wallets)).max(1))
+        .collect()
+}
+
+fn wallet_pubkey(keypair_filepath: &str) -> Result<String, String> {
+    read_keypair_file(keypair_filepath)
+        .map(|keypair| keypair.pubkey().to_string())
+        .map_err(|_| format!("no keypair found at {}", keypair_filepath))
+}
+
+// The latest totals of one wallet's session
+#[derive(Clone, Default)]
+struct WalletTotals {
+    threads: u64,
+    passes: u32,
+    ore_mined: f64,
+    sol_used: f64,
+    hashes: u64,
+    hash_rate: f64,
+}
+
+// The stats of every wallet mined by this process, updated by each wallet after each pass
+#[derive(Default)]
+pub struct CombinedStats {
+    wallets: Mutex<BTreeMap<String, WalletTotals>>,
+}
+
+impl CombinedStats {
+    pub fn update(&self, profile: &WalletProfile, session: &MiningSession) {
+        self.wallets.lock().unwrap().insert(profile.name.clone(), WalletTotals {
+            threads: profile.threads,
+            passes: session.passes,
+            ore_mined: session.session_ore_mined,
+            sol_used: session.session_sol_used,
+            hashes: session.session_hashes,
+            hash_rate: session.hash_rate(),
+        });
+    }
+
+    // A table of each wallet's session with the combined totals
+    pub fn summary(&self) -> StatsReport {
+        let wallets = self.wallets.lock().unwrap();
+        let mut report = StatsReport::new(format!("Combined stats for {} wallets at {}", wallets.len(), Local::now().format("%H:%M:%S on %Y-%m-%d")));
+        let mut table = ReportTable {
+            header: ["Threads", "Passes", "ORE mined", "SOL used", "Hashes", "H/s"].into_iter().map(Cell::plain).collect(),
+            rows: vec![],
+        };
+        let mut total = WalletTotals::default();
+        for (name, wallet) in wallets.iter() {
+            table.rows.push((name.clone(), totals_cells(wallet, Style::Plain)));
+            total.threads += wallet.threads;
+            total.passes += wallet.passes;
+            total.ore_mined += wallet.ore_mined;
+            total.sol_used += wallet.sol_used;
+            total.hashes += wallet.hashes;
+            total.hash_rate += wallet.hash_rate;
+        }
+        table.rows.push(("Total".to_string(), totals_cells(&total, Style::Bold)));
+        report.table(None, table);
+        report
+    }
+
+    // Show the combined stats and write them to the combined stats file, if there is one
+    fn log_summary(&self, logfile: &Option<String>) {
+        let summary = self.summary();
+        print!("\n{}", summary.render(ReportFormat::Terminal));
+        if let Some(logfile) = logfile {
+            let _result = write(logfile, summary.render(ReportFormat::Text));
+        }
+    }
+}
+
+fn totals_cells(wallet: &WalletTotals, style: Style) -> Vec<Cell> {
+    vec![
+        Cell::new(wallet.threads.to_string(), style),
+        Cell::new(wallet.passes.to_string(), style),
+        Cell::new(format!("{:.11}", wallet.ore_mined), Style::Good),
+        Cell::new(format!("{:.6}", wallet.sol_used), Style::Info),
+        Cell::new(wallet.hashes.to_string(), style),
+        Cell::new(format!("{:.2}", wallet.hash_rate), style),
+    ]
+}
+
+impl Miner {
+    // Mine with several wallets at once. Each wallet runs its own passes, scheduled from its own proof, on its
+    // share of the threads while the RPC client is shared. The combined stats are shown every few minutes.
+    pub async fn mine_wallets(&self, args: MineArgs, profiles: Vec<WalletProfile>, shutdown: Shutdown) -> StopReason {
+        let args = Arc::new(args);
+        let combined = Arc::new(CombinedStats::default());
+        let combined_logfile = env::var("STATS_LOGFILE").ok().filter(|f| !f.is_empty()).map(|f| format!("{}.combined", f));
+
+        println!("{}", format!("Mining with {} wallets: {}",
+            profiles.len(),
+            profiles.iter().map(|p| format!("{} ({} threads)", p.name, p.threads)).collect::<Vec<_>>().join(", "),
+        ).bold().green());
+
+        let mut wallets: FuturesUnordered<_> = profiles
+            .into_iter()
+            .enumerate()
+            .map(|(index, profile)| {
+                let mut miner = Miner::new(
+                    self.rpc_client.clone(),
+                    self.priority_fee,
+                    self.min_sol_balance,
+                    Some(profile.keypair_filepath.clone()),
+                );
+                miner.label = profile.label.clone();
+                let args = args.clone();
+                let shutdown = shutdown.clone();
+                let combined = combined.clone();
+                let name = profile.name.clone();
+                let handle = tokio::spawn(async move {
+                    miner.mine_wallet(&args, profile, shutdown, Some(combined)).await
+                });
+                async move { (index, name, handle.await) }
+            })
+            .collect();
+
+        let mut interval = tokio::time::interval(Duration::from_secs(COMBINED_STATS_SECS));
+        interval.tick().await;
+        let mut stop_reasons = vec![];
+        loop {
+            tokio::select! {
+                finished = wallets.next() => match finished {
+                    Some((index, _, Ok(stop_reason))) => stop_reasons.push((index, stop_reason)),
+                    // A wallet that has crashed would otherwise be left stopped while the other wallets keep the
+                    // process alive. Exit with an error so that miner.sh restarts all of them.
+                    Some((_, name, Err(err))) => {
+                        combined.log_summary(&combined_logfile);
+                        eprintln!("{} {} {}", "ERROR: Mining stopped unexpectedly for wallet".bold().red(), name, err);
+                        std::process::exit(1);
+                    }
+                    None => break,
+                },
+                _ = interval.tick() => combined.log_summary(&combined_logfile),
+            }
+        }
+
+        combined.log_summary(&combined_logfile);
+
+        // Every wallet stops for its own reason. Report the first wallet's so scripts see a familiar exit code.
+        stop_reasons
+            .into_iter()
+            .min_by_key(|(index, _)| *index)
+            .map(|(_, stop_reason)| stop_reason)
+            .unwrap_or(StopReason::Shutdown)
+    }
+}